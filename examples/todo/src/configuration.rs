@@ -4,6 +4,7 @@ use crate::persistence::TodoContext;
 use crate::todo::commands::{
     archive_todo, check_todo, create_todo, delete_archived_todos, rename_todo,
 };
+use crate::todo::queries::{get_summary, list_archived_todos, list_visible_todos};
 use crate::todo::views::{
     update_summary_on_todo_created, update_summary_on_todo_deleted, update_summary_on_todo_updated,
 };
@@ -19,4 +20,8 @@ pub fn configuration() -> Configuration<TodoContext, Error> {
         .command_handler(&check_todo)
         .command_handler(&archive_todo)
         .command_handler(&delete_archived_todos)
+        .query_handler(&get_summary)
+        .query_handler(&list_visible_todos)
+        .query_handler(&list_archived_todos)
+        .verify_declarations()
 }
@@ -1,22 +1,39 @@
+use presage::{ExecutionError, ValidationError};
 use std::sync::PoisonError;
 
 #[derive(Debug)]
-pub struct Error(pub String);
+pub enum Error {
+    Invalid(ValidationError),
+    Other(String),
+}
 
 impl From<presage::Error> for Error {
     fn from(error: presage::Error) -> Self {
-        Self(error.to_string())
+        match error {
+            presage::Error::InvalidCommand(_, error) => Self::Invalid(error),
+            error => Self::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<ExecutionError<Error>> for Error {
+    fn from(error: ExecutionError<Error>) -> Self {
+        let path = error.path().join(" > ");
+        match error.into_inner() {
+            Self::Other(message) => Self::Other(format!("{message} (at {path})")),
+            error => error,
+        }
     }
 }
 
 impl<G> From<PoisonError<G>> for Error {
     fn from(_: PoisonError<G>) -> Self {
-        Self("Concurrency error: the todo mutex has been poisoned".into())
+        Self::Other("Concurrency error: the todo mutex has been poisoned".into())
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
-        Error(format!("IO error: {error}"))
+        Self::Other(format!("IO error: {error}"))
     }
 }
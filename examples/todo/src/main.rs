@@ -7,12 +7,10 @@ mod todo;
 use dialoguer::console::Term;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Input, Select};
-use presage::Id;
 use std::io::Write;
-use time::OffsetDateTime;
-use uuid::Uuid;
 
 use crate::todo::commands::{ArchiveTodo, CheckTodo, DeleteArchivedTodos};
+use crate::todo::queries::{GetSummary, ListArchivedTodos, ListVisibleTodos};
 use crate::todo::{Todo, TodoState};
 use app::TodoApp;
 pub use error::Error;
@@ -22,6 +20,14 @@ const MENU: &[&str] = &["New todo", "List todos", "List archived todos", "Quit"]
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    if std::env::args().nth(1).as_deref() == Some("--diagram") {
+        print!(
+            "{}",
+            configuration::configuration().event_flow().to_mermaid()
+        );
+        return Ok(());
+    }
+
     let mut app = TodoApp::new();
 
     let mut term = Term::stdout();
@@ -29,7 +35,7 @@ async fn main() -> Result<(), Error> {
 
     loop {
         term.clear_screen()?;
-        writeln!(term, "{}", app.summary()?)?;
+        writeln!(term, "{}", app.query(GetSummary).await?)?;
 
         let selection = Select::with_theme(&theme)
             .with_prompt("Action:")
@@ -53,17 +59,7 @@ async fn new_todo(app: &mut TodoApp, term: &mut Term, theme: &ColorfulTheme) ->
         .with_prompt("New todo:")
         .interact_text_on(term)?;
 
-    let name = name.trim();
-
-    if !name.is_empty() {
-        app.execute(CreateTodo {
-            id: Id(Uuid::new_v4()),
-            name: name.into(),
-        })
-        .await
-    } else {
-        Ok(())
-    }
+    ignore_invalid(app.execute(CreateTodo::new(&name)).await)
 }
 
 async fn list_todos(
@@ -74,7 +70,7 @@ async fn list_todos(
     term.clear_screen()?;
     writeln!(term, "Todos:")?;
 
-    let mut todos = app.list_visible_todos()?;
+    let mut todos = app.query(ListVisibleTodos).await?;
     for (index, todo) in todos.iter().enumerate() {
         let state = if let TodoState::New = todo.state {
             '☐'
@@ -111,7 +107,7 @@ async fn edit_todo(
     };
 
     let selection = Select::with_theme(theme)
-        .with_prompt(&format!(r#"Edit "{}" (press <esc> to return)"#, todo.name))
+        .with_prompt(format!(r#"Edit "{}" (press <esc> to return)"#, todo.name))
         .default(0)
         .item("Rename")
         .item(state_action)
@@ -134,32 +130,21 @@ async fn rename(
         .with_prompt("New name:")
         .with_initial_text(todo.name)
         .interact_text_on(term)?;
-    let new_name = new_name.trim();
+    ignore_invalid(app.execute(RenameTodo::new(todo.id, &new_name)).await)
+}
 
-    if new_name.is_empty() {
-        Ok(())
-    } else {
-        app.execute(RenameTodo {
-            id: todo.id,
-            name: new_name.into(),
-        })
-        .await
+fn ignore_invalid(result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Err(Error::Invalid(_)) => Ok(()),
+        result => result,
     }
 }
 
 async fn next_state(todo: Todo, app: &mut TodoApp) -> Result<(), Error> {
     if matches!(todo.state, TodoState::New) {
-        app.execute(CheckTodo {
-            id: todo.id,
-            date: OffsetDateTime::now_utc(),
-        })
-        .await
+        app.execute(CheckTodo { id: todo.id }).await
     } else {
-        app.execute(ArchiveTodo {
-            id: todo.id,
-            date: OffsetDateTime::now_utc(),
-        })
-        .await
+        app.execute(ArchiveTodo { id: todo.id }).await
     }
 }
 
@@ -171,7 +156,7 @@ async fn list_archive(
     term.clear_screen()?;
     writeln!(term, "Archive:")?;
 
-    let todos = app.list_archived_todos()?;
+    let todos = app.query(ListArchivedTodos).await?;
     for (index, todo) in todos.iter().enumerate() {
         writeln!(term, "{:>2}. {}", index + 1, todo.name)?;
     }
@@ -1,19 +1,40 @@
-use presage::{async_trait, Aggregate, AggregateEvent, Event, EventWriter, Id, SerializedEvent};
+use presage::{
+    async_trait, Aggregate, AggregateEvent, Clock, Event, EventWriter, Id, IdGenerator,
+    SerializedEvent, SystemClock,
+};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::time::SystemTime;
+use uuid::Uuid;
 
 use crate::todo::events::{TodoCreated, TodoDeleted, TodoUpdated};
 use crate::todo::views::TodosSummary;
 use crate::todo::{Todo, TodoState};
 use crate::Error;
 
-#[derive(Default)]
 pub struct TodoContext {
     todos: HashMap<Id<Todo>, Todo>,
     summary: TodosSummary,
+    clock: Box<dyn Clock>,
+    ids: Box<dyn IdGenerator<Uuid>>,
+}
+
+impl Default for TodoContext {
+    fn default() -> Self {
+        Self::new(Box::new(SystemClock), Box::new(Uuid::new_v4))
+    }
 }
 
 impl TodoContext {
+    pub fn new(clock: Box<dyn Clock>, ids: Box<dyn IdGenerator<Uuid>>) -> Self {
+        Self {
+            todos: HashMap::new(),
+            summary: TodosSummary::default(),
+            clock,
+            ids,
+        }
+    }
+
     pub fn get(&self, id: Id<Todo>) -> Option<Todo> {
         self.todos.get(&id).cloned()
     }
@@ -24,7 +45,10 @@ impl TodoContext {
                 entry.insert(todo);
                 Ok(())
             }
-            _ => Err(Error(format!("A todo with id {} already exists", todo.id))),
+            _ => Err(Error::Other(format!(
+                "A todo with id {} already exists",
+                todo.id
+            ))),
         }
     }
 
@@ -34,7 +58,7 @@ impl TodoContext {
                 entry.insert(todo);
                 Ok(())
             }
-            _ => Err(Error(format!("Todo {} was not found", todo.id))),
+            _ => Err(Error::Other(format!("Todo {} was not found", todo.id))),
         }
     }
 
@@ -94,3 +118,15 @@ impl EventWriter for TodoContext {
         Ok(())
     }
 }
+
+impl Clock for TodoContext {
+    fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+}
+
+impl IdGenerator<Id<Todo>> for TodoContext {
+    fn next_id(&self) -> Id<Todo> {
+        Id(self.ids.next_id())
+    }
+}
@@ -1,4 +1,4 @@
-use presage::{command_handler, events, Command, Event, Events, Id};
+use presage::{command_handler, events, Clock, Command, Event, Events, Id, IdGenerator};
 use time::OffsetDateTime;
 
 use super::events::{TodoCreated, TodoUpdated};
@@ -7,31 +7,54 @@ use crate::persistence::TodoContext;
 use crate::todo::events::TodoDeleted;
 use crate::Error;
 
-#[derive(Debug, Command)]
+#[derive(Debug, Clone, Command)]
+#[presage(validate)]
 pub struct CreateTodo {
-    pub id: Id<Todo>,
+    #[validate(not_blank)]
     pub name: String,
 }
 
-#[command_handler]
+impl CreateTodo {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.trim().into(),
+        }
+    }
+}
+
+#[command_handler(emits = TodoCreated)]
 pub async fn create_todo(
-    _context: &mut TodoContext,
-    CreateTodo { id, name }: CreateTodo,
+    context: &mut TodoContext,
+    CreateTodo { name }: CreateTodo,
 ) -> Result<Events, Error> {
-    Ok(events!(TodoCreated { id, name }))
+    Ok(events!(TodoCreated {
+        id: context.next_id(),
+        name
+    }))
 }
 
-#[derive(Debug, Command)]
+#[derive(Debug, Clone, Command)]
+#[presage(validate)]
 pub struct RenameTodo {
     pub id: Id<Todo>,
+    #[validate(not_blank)]
     pub name: String,
 }
 
-#[command_handler]
+impl RenameTodo {
+    pub fn new(id: Id<Todo>, name: &str) -> Self {
+        Self {
+            id,
+            name: name.trim().into(),
+        }
+    }
+}
+
+#[command_handler(emits = TodoUpdated)]
 pub async fn rename_todo(context: &mut TodoContext, command: RenameTodo) -> Result<Events, Error> {
     let todo = context
         .get(command.id)
-        .ok_or_else(|| Error(format!("Todo with id {} does not exist", command.id)))?;
+        .ok_or_else(|| Error::Other(format!("Todo with id {} does not exist", command.id)))?;
     if todo.name != command.name {
         Ok(events!(TodoUpdated::Renamed {
             id: todo.id,
@@ -43,57 +66,58 @@ pub async fn rename_todo(context: &mut TodoContext, command: RenameTodo) -> Resu
     }
 }
 
-#[derive(Debug, Command)]
+#[derive(Debug, Clone, Command)]
 pub struct CheckTodo {
     pub id: Id<Todo>,
-    pub date: OffsetDateTime,
 }
 
-#[command_handler]
+#[command_handler(emits = TodoUpdated)]
 pub async fn check_todo(
     context: &mut TodoContext,
-    CheckTodo { id, date }: CheckTodo,
+    CheckTodo { id }: CheckTodo,
 ) -> Result<Events, Error> {
     let todo = context
         .get(id)
-        .ok_or_else(|| Error(format!("Todo with id {} does not exist", id)))?;
+        .ok_or_else(|| Error::Other(format!("Todo with id {} does not exist", id)))?;
     if let TodoState::New = todo.state {
-        Ok(events!(TodoUpdated::Done(id, date)))
+        Ok(events!(TodoUpdated::Done(
+            id,
+            OffsetDateTime::from(context.now())
+        )))
     } else {
         Ok(Default::default())
     }
 }
 
-#[derive(Debug, Command)]
+#[derive(Debug, Clone, Command)]
 #[presage(name = "archive-done-todo")]
 pub struct ArchiveTodo {
     pub id: Id<Todo>,
-    pub date: OffsetDateTime,
 }
 
-#[command_handler]
+#[command_handler(emits = TodoUpdated)]
 pub async fn archive_todo(
     context: &mut TodoContext,
-    ArchiveTodo { id, date }: ArchiveTodo,
+    ArchiveTodo { id }: ArchiveTodo,
 ) -> Result<Events, Error> {
     let todo = context
         .get(id)
-        .ok_or_else(|| Error(format!("Todo with id {} does not exist", id)))?;
+        .ok_or_else(|| Error::Other(format!("Todo with id {} does not exist", id)))?;
     if let TodoState::Done { done_date } = todo.state {
         Ok(events!(TodoUpdated::Archived {
             id: todo.id,
             done_date,
-            archived_date: date,
+            archived_date: OffsetDateTime::from(context.now()),
         }))
     } else {
         Ok(Default::default())
     }
 }
 
-#[derive(Debug, Command)]
+#[derive(Debug, Clone, Command)]
 pub struct DeleteArchivedTodos;
 
-#[command_handler]
+#[command_handler(emits = TodoDeleted)]
 pub async fn delete_archived_todos(
     context: &mut TodoContext,
     _: DeleteArchivedTodos,
@@ -105,3 +129,47 @@ pub async fn delete_archived_todos(
         .collect::<Result<_, _>>()?;
     Ok(Events(events))
 }
+
+#[cfg(test)]
+mod test {
+    use presage::testing::Scenario;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::configuration::configuration;
+
+    const ID: Id<Todo> = Id(Uuid::from_u128(1));
+
+    #[tokio::test]
+    async fn test_rename_todo() -> Result<(), Error> {
+        Scenario::new(configuration(), TodoContext::default())
+            .given(events!(TodoCreated {
+                id: ID,
+                name: "Write tests".into()
+            }))
+            .when(RenameTodo::new(ID, "Write more tests"))
+            .await
+            .then_expect(events!(TodoUpdated::Renamed {
+                id: ID,
+                old_name: "Write tests".into(),
+                new_name: "Write more tests".into()
+            }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rename_missing_todo() {
+        Scenario::new(configuration(), TodoContext::default())
+            .when(RenameTodo::new(ID, "Write more tests"))
+            .await
+            .then_error(|error| matches!(error, Error::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_rename_todo_with_blank_name() {
+        Scenario::new(configuration(), TodoContext::default())
+            .when(RenameTodo::new(ID, " "))
+            .await
+            .then_error(|error| matches!(error, Error::Invalid(_)));
+    }
+}
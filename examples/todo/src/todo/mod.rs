@@ -1,5 +1,6 @@
 pub mod commands;
 pub mod events;
+pub mod queries;
 pub mod views;
 
 use presage::{Aggregate, Id};
@@ -78,3 +79,44 @@ impl Ord for Todo {
             .then_with(|| self.id.cmp(&other.id))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use presage::testing::AggregateFixture;
+    use time::macros::datetime;
+
+    use super::*;
+
+    const ID: Id<Todo> = Id(Uuid::from_u128(1));
+
+    #[test]
+    fn test_todo_lifecycle() {
+        let done_date = datetime!(2023-05-01 10:00 UTC);
+        let archived_date = datetime!(2023-05-08 10:00 UTC);
+
+        AggregateFixture::new(TodoCreated {
+            id: ID,
+            name: "Write tests".into(),
+        })
+        .then(|todo| assert_eq!(todo.state, TodoState::New))
+        .apply_all([
+            TodoUpdated::Done(ID, done_date),
+            TodoUpdated::Archived {
+                id: ID,
+                done_date,
+                archived_date,
+            },
+        ])
+        .then(|todo| {
+            assert_eq!(
+                todo.state,
+                TodoState::Archived {
+                    done_date,
+                    archived_date
+                }
+            )
+        })
+        .delete(TodoDeleted(ID))
+        .then_deleted();
+    }
+}
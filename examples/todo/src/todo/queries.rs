@@ -0,0 +1,71 @@
+use presage::{query_handler, Query};
+
+use crate::persistence::TodoContext;
+use crate::todo::views::TodosSummary;
+use crate::todo::Todo;
+use crate::Error;
+
+#[derive(Debug, Query)]
+#[presage(output = TodosSummary)]
+pub struct GetSummary;
+
+#[derive(Debug, Query)]
+#[presage(output = Vec<Todo>)]
+pub struct ListVisibleTodos;
+
+#[derive(Debug, Query)]
+#[presage(output = Vec<Todo>)]
+pub struct ListArchivedTodos;
+
+#[query_handler]
+pub async fn get_summary(context: &TodoContext, _: GetSummary) -> Result<TodosSummary, Error> {
+    Ok(context.summary())
+}
+
+#[query_handler]
+pub async fn list_visible_todos(
+    context: &TodoContext,
+    _: ListVisibleTodos,
+) -> Result<Vec<Todo>, Error> {
+    Ok(context.list_visible_todos())
+}
+
+#[query_handler]
+pub async fn list_archived_todos(
+    context: &TodoContext,
+    _: ListArchivedTodos,
+) -> Result<Vec<Todo>, Error> {
+    Ok(context.list_archived_todos())
+}
+
+#[cfg(test)]
+mod test {
+    use presage::{CommandBus, QueryBus};
+
+    use super::*;
+    use crate::configuration::configuration;
+    use crate::todo::commands::{ArchiveTodo, CheckTodo, CreateTodo};
+
+    #[tokio::test]
+    async fn test_archived_todos_are_not_visible() -> Result<(), Error> {
+        let command_bus = CommandBus::new().configure(configuration());
+        let query_bus = QueryBus::new().configure(configuration());
+        let mut context = TodoContext::default();
+        command_bus
+            .execute(&mut context, CreateTodo::new("Write tests"))
+            .await?;
+        let id = query_bus.execute(&context, ListVisibleTodos).await?[0].id;
+        command_bus.execute(&mut context, CheckTodo { id }).await?;
+        command_bus
+            .execute(&mut context, ArchiveTodo { id })
+            .await?;
+
+        let visible = query_bus.execute(&context, ListVisibleTodos).await?;
+        let archived = query_bus.execute(&context, ListArchivedTodos).await?;
+
+        assert!(visible.is_empty());
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, id);
+        Ok(())
+    }
+}
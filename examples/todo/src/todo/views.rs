@@ -66,3 +66,59 @@ pub async fn update_summary_on_todo_deleted(
     context.save_summary(summary);
     Ok(Commands::default())
 }
+
+#[cfg(test)]
+mod test {
+    use presage::testing::{arbitrary_command, PropertyTest};
+    use presage::{Id, IdGenerator, SequentialIdGenerator, SystemClock};
+    use proptest::prelude::*;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::configuration::configuration;
+    use crate::todo::commands::{
+        ArchiveTodo, CheckTodo, CreateTodo, DeleteArchivedTodos, RenameTodo,
+    };
+    use crate::todo::{Todo, TodoState};
+
+    #[tokio::test]
+    async fn test_summary_matches_todos() {
+        PropertyTest::new(configuration(), context)
+            .invariant("the summary matches the todos", |context| {
+                let summary = context.summary();
+                let todos = context.list_visible_todos();
+                let new = todos
+                    .iter()
+                    .filter(|todo| todo.state == TodoState::New)
+                    .count();
+                summary.new == new
+                    && summary.done == todos.len() - new
+                    && summary.archived == context.list_archived_todos().len()
+            })
+            .check(prop::collection::vec(
+                prop_oneof![
+                    arbitrary_command("[a-z]{1,8}".prop_map(|name| CreateTodo::new(&name))),
+                    arbitrary_command(
+                        (id(), "[a-z]{1,8}").prop_map(|(id, name)| RenameTodo::new(id, &name))
+                    ),
+                    arbitrary_command(id().prop_map(|id| CheckTodo { id })),
+                    arbitrary_command(id().prop_map(|id| ArchiveTodo { id })),
+                    arbitrary_command(Just(DeleteArchivedTodos)),
+                ],
+                0..20,
+            ))
+            .await;
+    }
+
+    fn context() -> TodoContext {
+        let ids = SequentialIdGenerator::new(1);
+        TodoContext::new(
+            Box::new(SystemClock),
+            Box::new(move || Uuid::from_u128(IdGenerator::<u64>::next_id(&ids).into())),
+        )
+    }
+
+    fn id() -> impl Strategy<Value = Id<Todo>> {
+        (1..5u128).prop_map(|id| Id(Uuid::from_u128(id)))
+    }
+}
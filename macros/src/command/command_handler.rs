@@ -1,9 +1,10 @@
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
-use syn::{parse_macro_input, Generics, Ident, ItemFn, Signature, Token, Type};
+use syn::token::Bracket;
+use syn::{bracketed, parse_macro_input, Generics, Ident, ItemFn, LitStr, Signature, Token, Type};
 
-use crate::utils::{error, extract_error_type, extract_input, HandlerInput};
+use crate::utils::{error, extract_error_type, extract_input, parse_names, HandlerInput, Name};
 
 pub fn command_handler(arguments: TokenStream, handler: TokenStream) -> TokenStream {
     let arguments = parse_macro_input!(arguments as CommandHandlerArguments);
@@ -56,6 +57,36 @@ pub fn command_handler(arguments: TokenStream, handler: TokenStream) -> TokenStr
         None => return error(handler_name, MISSING_ERROR_TYPE),
     };
 
+    let requires = arguments.requires.map(|roles| {
+        quote! {
+            fn requires(&self) -> &[&'static str] {
+                &[#(#roles),*]
+            }
+        }
+    });
+
+    let emits = arguments.emits.map(|events| {
+        let names = events
+            .iter()
+            .map(|event| event.to_expression(quote! {presage::Event}));
+        let event_types: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Name::Type(event_type) => Some(event_type),
+                Name::Literal(_) => None,
+            })
+            .collect();
+        let event_schemas = (!event_types.is_empty())
+            .then(|| quote! { presage::__event_schemas!(#(#event_types),*); });
+        quote! {
+            fn emits(&self) -> Option<&[&'static str]> {
+                Some(&[#(#names),*])
+            }
+
+            #event_schemas
+        }
+    });
+
     TokenStream::from(quote! {
         #(#attrs)*
         #[allow(non_camel_case_types)]
@@ -63,10 +94,20 @@ pub fn command_handler(arguments: TokenStream, handler: TokenStream) -> TokenStr
 
         #[presage::async_trait]
         impl<#params> presage::CommandHandler<#context_type, #error_type> for #handler_name #where_clause {
+            fn name(&self) -> &'static str {
+                stringify!(#handler_name)
+            }
+
             fn command_name(&self) -> &'static str {
                 <#parameter_type as presage::Command>::NAME
             }
 
+            #requires
+
+            #emits
+
+            presage::__command_schema!(#parameter_type);
+
             async fn handle(&self, #context: &mut #context_type, command: presage::BoxedCommand) #output {
                 let #parameter: #parameter_type = command.downcast()?;
                 #block
@@ -78,23 +119,54 @@ pub fn command_handler(arguments: TokenStream, handler: TokenStream) -> TokenStr
 #[derive(Default)]
 struct CommandHandlerArguments {
     error: Option<Type>,
+    requires: Option<Vec<LitStr>>,
+    emits: Option<Vec<Name>>,
 }
 
 impl Parse for CommandHandlerArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        if input.is_empty() {
-            Ok(Default::default())
-        } else {
-            let argument = input.parse::<Ident>()?;
-            if argument != "error" {
-                Err(syn::Error::new_spanned(argument, "unexpected argument"))
-            } else {
-                input.parse::<Token![=]>()?;
-                Ok(CommandHandlerArguments {
-                    error: Some(input.parse()?),
-                })
+        let mut arguments = CommandHandlerArguments::default();
+
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            match ident.to_string().as_str() {
+                "error" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.error = Some(input.parse()?);
+                }
+                "requires" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.requires = Some(parse_roles(input)?);
+                }
+                "emits" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.emits = Some(parse_names(input)?);
+                }
+                _ => return Err(syn::Error::new_spanned(ident, "unknown argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
             }
         }
+
+        Ok(arguments)
+    }
+}
+
+fn parse_roles(input: ParseStream) -> syn::Result<Vec<LitStr>> {
+    let roles: Vec<LitStr> = if input.peek(Bracket) {
+        let content;
+        bracketed!(content in input);
+        content
+            .parse_terminated(<LitStr as Parse>::parse, Token![,])?
+            .into_iter()
+            .collect()
+    } else {
+        vec![input.parse()?]
+    };
+    match roles.iter().find(|role| role.value().trim().is_empty()) {
+        Some(role) => Err(syn::Error::new_spanned(role, "a role must not be empty")),
+        None => Ok(roles),
     }
 }
 
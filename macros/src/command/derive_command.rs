@@ -1,43 +1,79 @@
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
-use syn::{parse_macro_input, Attribute, Ident, Item, LitStr, Token};
+use syn::{parse_macro_input, Attribute, Fields, Ident, Item, LitStr, Token};
 
+use crate::command::validate::{ValidateArgument, Validation};
+use crate::schema::{derive_json_schema, schema_method};
 use crate::utils::{create_str_literal_from_ident, error, has_name};
 
 pub fn derive_command(command: TokenStream) -> TokenStream {
     let item = parse_macro_input!(command as Item);
+    let source = item.clone();
 
     let CommandInfo {
         type_name,
         command_name,
+        retryable,
+        schema,
+        validation,
     } = match item.try_into() {
         Ok(info) => info,
         Err(error) => return error,
     };
 
+    let try_clone = retryable.then(|| {
+        quote! {
+            fn try_clone(&self) -> Option<Self> {
+                Some(Clone::clone(self))
+            }
+        }
+    });
+
+    let schema_method = schema.then(schema_method);
+    let json_schema = schema.then(|| derive_json_schema(&source));
+
     TokenStream::from(quote! {
         impl presage::Command for #type_name {
             const NAME: &'static str = #command_name;
+
+            #try_clone
+
+            #schema_method
         }
+
+        #validation
+
+        #json_schema
     })
 }
 
 struct CommandInfo {
     type_name: Ident,
     command_name: LitStr,
+    retryable: bool,
+    schema: bool,
+    validation: Validation,
 }
 
 impl CommandInfo {
-    fn try_from(type_name: Ident, attributes: &[Attribute]) -> Result<Self, TokenStream> {
+    fn try_from(
+        type_name: Ident,
+        attributes: &[Attribute],
+        fields: Option<&Fields>,
+    ) -> Result<Self, TokenStream> {
         let arguments =
             DeriveCommandArguments::try_from(attributes).map_err(syn::Error::into_compile_error)?;
         let command_name = arguments
             .command_name
             .unwrap_or_else(|| create_str_literal_from_ident(&type_name));
+        let validation = Validation::try_from(&type_name, arguments.validate, fields)?;
         Ok(CommandInfo {
             type_name,
             command_name,
+            retryable: arguments.retryable,
+            schema: arguments.schema,
+            validation,
         })
     }
 }
@@ -47,8 +83,10 @@ impl TryFrom<Item> for CommandInfo {
 
     fn try_from(item: Item) -> Result<Self, Self::Error> {
         match item {
-            Item::Struct(item) => CommandInfo::try_from(item.ident, &item.attrs),
-            Item::Enum(item) => CommandInfo::try_from(item.ident, &item.attrs),
+            Item::Struct(item) => {
+                CommandInfo::try_from(item.ident, &item.attrs, Some(&item.fields))
+            }
+            Item::Enum(item) => CommandInfo::try_from(item.ident, &item.attrs, None),
             _ => Err(error(
                 item,
                 "Command can only be derived for a struct or an enum",
@@ -60,23 +98,33 @@ impl TryFrom<Item> for CommandInfo {
 #[derive(Default)]
 struct DeriveCommandArguments {
     command_name: Option<LitStr>,
+    retryable: bool,
+    schema: bool,
+    validate: Option<ValidateArgument>,
 }
 
 impl Parse for DeriveCommandArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        if input.is_empty() {
-            Ok(DeriveCommandArguments::default())
-        } else {
+        let mut arguments = DeriveCommandArguments::default();
+
+        while !input.is_empty() {
             let argument = input.parse::<Ident>()?;
-            if argument != "name" {
-                Err(syn::Error::new_spanned(argument, "unexpected argument"))
-            } else {
-                input.parse::<Token![=]>()?;
-                Ok(DeriveCommandArguments {
-                    command_name: Some(input.parse()?),
-                })
+            match argument.to_string().as_str() {
+                "name" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.command_name = Some(input.parse()?);
+                }
+                "retryable" => arguments.retryable = true,
+                "schema" => arguments.schema = true,
+                "validate" => arguments.validate = Some(ValidateArgument::parse(argument, input)?),
+                _ => return Err(syn::Error::new_spanned(argument, "unexpected argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
             }
         }
+
+        Ok(arguments)
     }
 }
 
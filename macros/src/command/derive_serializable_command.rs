@@ -0,0 +1,22 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Item};
+
+use crate::utils::error;
+
+pub fn derive_serializable_command(command: TokenStream) -> TokenStream {
+    let type_name = match parse_macro_input!(command as Item) {
+        Item::Struct(item) => item.ident,
+        Item::Enum(item) => item.ident,
+        item => {
+            return error(
+                item,
+                "SerializableCommand can only be derived for a struct or an enum",
+            )
+        }
+    };
+
+    TokenStream::from(quote! {
+        impl presage::SerializableCommand for #type_name {}
+    })
+}
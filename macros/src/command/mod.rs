@@ -1,2 +1,4 @@
 pub mod command_handler;
 pub mod derive_command;
+pub mod derive_serializable_command;
+pub mod validate;
@@ -0,0 +1,226 @@
+use proc_macro::TokenStream;
+use quote::{quote, ToTokens, TokenStreamExt};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Paren;
+use syn::{parenthesized, Expr, Fields, Ident, LitStr, Member, Token};
+
+use crate::utils::{error, has_name};
+
+pub enum Validation {
+    Default(Ident),
+    Rules(Ident, Vec<FieldRules>),
+    Manual,
+}
+
+/// The `validate` argument of `#[presage(...)]`: `validate` to derive the validation from rules, or
+/// `validate = manual` to implement `Validate` by hand.
+pub enum ValidateArgument {
+    Rules(Ident),
+    Manual,
+}
+
+impl ValidateArgument {
+    /// Parses the rest of the `validate` argument, after its name.
+    pub fn parse(validate: Ident, input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            let mode = input.parse::<Ident>()?;
+            if mode == "manual" {
+                Ok(ValidateArgument::Manual)
+            } else {
+                Err(syn::Error::new_spanned(mode, "expected `manual`"))
+            }
+        } else {
+            Ok(ValidateArgument::Rules(validate))
+        }
+    }
+}
+
+impl Validation {
+    pub fn try_from(
+        type_name: &Ident,
+        validate: Option<ValidateArgument>,
+        fields: Option<&Fields>,
+    ) -> Result<Self, TokenStream> {
+        let rules = match fields {
+            Some(fields) => fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    let member = match &field.ident {
+                        Some(ident) => Member::Named(ident.clone()),
+                        None => Member::Unnamed(index.into()),
+                    };
+                    FieldRules::try_from(member, &field.attrs)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        let rules: Vec<_> = rules.into_iter().flatten().collect();
+
+        match validate {
+            None => match rules.first() {
+                Some(field) => Err(error(&field.member, MISSING_VALIDATE_ARGUMENT)),
+                None => Ok(Validation::Default(type_name.clone())),
+            },
+            Some(ValidateArgument::Manual) => match rules.first() {
+                Some(field) => Err(error(&field.member, MANUAL_VALIDATION_RULES)),
+                None => Ok(Validation::Manual),
+            },
+            Some(ValidateArgument::Rules(validate)) if fields.is_none() => Err(error(
+                validate,
+                "validation rules can only be derived for a struct",
+            )),
+            Some(ValidateArgument::Rules(_)) => Ok(Validation::Rules(type_name.clone(), rules)),
+        }
+    }
+}
+
+impl ToTokens for Validation {
+    fn to_tokens(&self, tokens: &mut quote::__private::TokenStream) {
+        match self {
+            Self::Default(type_name) => tokens.append_all(quote! {
+                impl presage::Validate for #type_name {}
+            }),
+            Self::Rules(type_name, rules) => tokens.append_all(quote! {
+                impl presage::Validate for #type_name {
+                    fn validate(&self) -> Result<(), presage::ValidationError> {
+                        let mut error = presage::ValidationError::new();
+                        #(#rules)*
+                        error.into_result()
+                    }
+                }
+            }),
+            Self::Manual => {}
+        }
+    }
+}
+
+pub struct FieldRules {
+    member: Member,
+    rules: Vec<Rule>,
+}
+
+impl FieldRules {
+    fn try_from(
+        member: Member,
+        attributes: &[syn::Attribute],
+    ) -> Result<Option<Self>, TokenStream> {
+        let mut rules = Vec::new();
+        for attribute in attributes {
+            if has_name(attribute, "validate") {
+                let parsed = attribute
+                    .parse_args_with(Punctuated::<Rule, Token![,]>::parse_terminated)
+                    .map_err(syn::Error::into_compile_error)?;
+                rules.extend(parsed);
+            }
+        }
+        Ok((!rules.is_empty()).then_some(FieldRules { member, rules }))
+    }
+}
+
+impl ToTokens for FieldRules {
+    fn to_tokens(&self, tokens: &mut quote::__private::TokenStream) {
+        let member = &self.member;
+        let field_name = match member {
+            Member::Named(ident) => LitStr::new(&ident.to_string(), ident.span()),
+            Member::Unnamed(index) => LitStr::new(&index.index.to_string(), index.span),
+        };
+        for rule in &self.rules {
+            tokens.append_all(match rule {
+                Rule::NonEmpty => quote! {
+                    if self.#member.is_empty() {
+                        error.add(#field_name, "must not be empty");
+                    }
+                },
+                Rule::NotBlank => quote! {
+                    if self.#member.trim().is_empty() {
+                        error.add(#field_name, "must not be blank");
+                    }
+                },
+                Rule::Range { min, max } => {
+                    let min = min.as_ref().map(|min| {
+                        quote! {
+                            if self.#member < #min {
+                                error.add(
+                                    #field_name,
+                                    concat!("must be greater than or equal to ", stringify!(#min)),
+                                );
+                            }
+                        }
+                    });
+                    let max = max.as_ref().map(|max| {
+                        quote! {
+                            if self.#member > #max {
+                                error.add(
+                                    #field_name,
+                                    concat!("must be less than or equal to ", stringify!(#max)),
+                                );
+                            }
+                        }
+                    });
+                    quote! { #min #max }
+                }
+            })
+        }
+    }
+}
+
+enum Rule {
+    NonEmpty,
+    NotBlank,
+    Range {
+        min: Option<Box<Expr>>,
+        max: Option<Box<Expr>>,
+    },
+}
+
+impl Parse for Rule {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse::<Ident>()?;
+        match ident.to_string().as_str() {
+            "non_empty" => Ok(Rule::NonEmpty),
+            "not_blank" => Ok(Rule::NotBlank),
+            "range" if input.peek(Paren) => {
+                let content;
+                parenthesized!(content in input);
+                let mut min = None;
+                let mut max = None;
+                while !content.is_empty() {
+                    let bound = content.parse::<Ident>()?;
+                    content.parse::<Token![=]>()?;
+                    match bound.to_string().as_str() {
+                        "min" => min = Some(content.parse()?),
+                        "max" => max = Some(content.parse()?),
+                        _ => return Err(syn::Error::new_spanned(bound, "unknown bound")),
+                    }
+                    if content.peek(Token![,]) {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+                if min.is_none() && max.is_none() {
+                    Err(syn::Error::new_spanned(
+                        ident,
+                        "a range requires a `min` or a `max` bound",
+                    ))
+                } else {
+                    Ok(Rule::Range { min, max })
+                }
+            }
+            _ => Err(syn::Error::new_spanned(ident, UNKNOWN_RULE)),
+        }
+    }
+}
+
+const MISSING_VALIDATE_ARGUMENT: &str = r"Validation rules are only applied to commands with the #[presage(validate)] attribute.
+
+help: add `#[presage(validate)]` on the type";
+
+const MANUAL_VALIDATION_RULES: &str = r"Validation rules cannot be used with manual validation.
+
+help: remove the `#[validate]` attributes, or use `#[presage(validate)]` on the type";
+
+const UNKNOWN_RULE: &str = r"Unknown validation rule.
+
+help: use `non_empty`, `not_blank` or `range(min = <expr>, max = <expr>)`";
@@ -7,24 +7,32 @@ use syn::{
     ItemStruct, LitStr, Path, Token, Variant,
 };
 
+use crate::schema::{derive_json_schema, schema_method};
 use crate::utils::{create_str_literal_from_ident, error, has_name};
 
 pub fn derive_aggregate_event(event: TokenStream) -> TokenStream {
     let item = parse_macro_input!(event as Item);
+    let source = item.clone();
 
     let AggregateEventInfo {
         type_name,
         aggregate,
         id_spec,
         event_name,
+        schema,
     } = match item.try_into() {
         Ok(info) => info,
         Err(error) => return error,
     };
 
+    let schema_method = schema.then(schema_method);
+    let json_schema = schema.then(|| derive_json_schema(&source));
+
     TokenStream::from(quote! {
         impl presage::Event for #type_name {
             const NAME: &'static str = #event_name;
+
+            #schema_method
         }
 
         impl presage::AggregateEvent for #type_name {
@@ -34,6 +42,8 @@ pub fn derive_aggregate_event(event: TokenStream) -> TokenStream {
                 #id_spec
             }
         }
+
+        #json_schema
     })
 }
 
@@ -42,25 +52,27 @@ struct DeriveAggregateEventArguments {
     aggregate: Option<Path>,
     id: Option<Ident>,
     event_name: Option<LitStr>,
+    schema: bool,
 }
 
 impl Parse for DeriveAggregateEventArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut aggregate = None;
+        let mut id = None;
+        let mut event_name = None;
+        let mut schema = false;
+
         let ahead = input.fork();
         if let Ok(path) = ahead.parse::<Path>() {
-            if ahead.is_empty() {
+            if !path.is_ident("schema") && (ahead.is_empty() || ahead.peek(Token![,])) {
                 input.advance_to(&ahead);
-                return Ok(DeriveAggregateEventArguments {
-                    aggregate: Some(path),
-                    ..Default::default()
-                });
+                aggregate = Some(path);
+                if input.peek(Token![,]) {
+                    input.parse::<Token![,]>()?;
+                }
             }
         }
 
-        let mut aggregate = None;
-        let mut id = None;
-        let mut event_name = None;
-
         while !input.is_empty() {
             let ident = input.parse::<Ident>()?;
             match ident.to_string().as_str() {
@@ -83,6 +95,7 @@ impl Parse for DeriveAggregateEventArguments {
                     }
                     event_name = Some(value);
                 }
+                "schema" => schema = true,
                 _ => return Err(syn::Error::new_spanned(ident, "unknown argument")),
             }
             if input.peek(Token![,]) {
@@ -94,6 +107,7 @@ impl Parse for DeriveAggregateEventArguments {
             aggregate,
             id,
             event_name,
+            schema,
         })
     }
 }
@@ -116,6 +130,7 @@ struct AggregateEventInfo {
     aggregate: Path,
     id_spec: IdSpec,
     event_name: LitStr,
+    schema: bool,
 }
 
 impl TryFrom<Item> for AggregateEventInfo {
@@ -158,6 +173,7 @@ impl TryFrom<ItemStruct> for AggregateEventInfo {
             aggregate,
             id_spec,
             event_name,
+            schema: arguments.schema,
         })
     }
 }
@@ -189,6 +205,7 @@ impl TryFrom<ItemEnum> for AggregateEventInfo {
             aggregate,
             id_spec,
             event_name,
+            schema: arguments.schema,
         })
     }
 }
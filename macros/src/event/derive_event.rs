@@ -3,46 +3,62 @@ use quote::quote;
 use syn::parse::{Parse, ParseStream};
 use syn::{parse_macro_input, Attribute, Ident, Item, ItemEnum, ItemStruct, LitStr, Token};
 
+use crate::schema::{derive_json_schema, schema_method};
 use crate::utils::{create_str_literal_from_ident, error, has_name};
 
 pub fn derive_event(event: TokenStream) -> TokenStream {
     let item = parse_macro_input!(event as Item);
+    let source = item.clone();
 
     let EventInfo {
         type_name,
         event_name,
+        schema,
     } = match item.try_into() {
         Ok(info) => info,
         Err(error) => return error,
     };
 
+    let schema_method = schema.then(schema_method);
+    let json_schema = schema.then(|| derive_json_schema(&source));
+
     TokenStream::from(quote! {
         impl presage::Event for #type_name {
             const NAME: &'static str = #event_name;
+
+            #schema_method
         }
+
+        #json_schema
     })
 }
 
 #[derive(Default)]
 struct DeriveEventArguments {
     event_name: Option<LitStr>,
+    schema: bool,
 }
 
 impl Parse for DeriveEventArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        if input.is_empty() {
-            Ok(DeriveEventArguments::default())
-        } else {
+        let mut arguments = DeriveEventArguments::default();
+
+        while !input.is_empty() {
             let argument = input.parse::<Ident>()?;
-            if argument != "name" {
-                Err(syn::Error::new_spanned(argument, "unexpected argument"))
-            } else {
-                input.parse::<Token![=]>()?;
-                Ok(DeriveEventArguments {
-                    event_name: Some(input.parse()?),
-                })
+            match argument.to_string().as_str() {
+                "name" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.event_name = Some(input.parse()?);
+                }
+                "schema" => arguments.schema = true,
+                _ => return Err(syn::Error::new_spanned(argument, "unexpected argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
             }
         }
+
+        Ok(arguments)
     }
 }
 
@@ -62,6 +78,7 @@ impl TryFrom<&[Attribute]> for DeriveEventArguments {
 struct EventInfo {
     type_name: Ident,
     event_name: LitStr,
+    schema: bool,
 }
 
 impl TryFrom<Item> for EventInfo {
@@ -93,6 +110,7 @@ impl TryFrom<ItemStruct> for EventInfo {
         Ok(EventInfo {
             type_name: item.ident,
             event_name,
+            schema: arguments.schema,
         })
     }
 }
@@ -111,6 +129,7 @@ impl TryFrom<ItemEnum> for EventInfo {
         Ok(EventInfo {
             type_name: item.ident,
             event_name,
+            schema: arguments.schema,
         })
     }
 }
@@ -1,11 +1,9 @@
 use proc_macro::TokenStream;
-use quote::{quote, ToTokens, TokenStreamExt};
-use syn::parse::discouraged::Speculative;
+use quote::quote;
 use syn::parse::{Parse, ParseStream};
-use syn::token::Bracket;
-use syn::{bracketed, parse_macro_input, Generics, Ident, ItemFn, LitStr, Signature, Token, Type};
+use syn::{parse_macro_input, Generics, Ident, ItemFn, Signature, Token, Type};
 
-use crate::utils::{error, extract_error_type, extract_input, HandlerInput};
+use crate::utils::{error, extract_error_type, extract_input, parse_names, HandlerInput, Name};
 
 pub fn event_handler(arguments: TokenStream, handler: TokenStream) -> TokenStream {
     let arguments = parse_macro_input!(arguments as EventHandlerArguments);
@@ -72,7 +70,27 @@ pub fn event_handler(arguments: TokenStream, handler: TokenStream) -> TokenStrea
 
     let event_names = arguments
         .event_names
-        .unwrap_or_else(|| vec![EventName::Event(parameter_type.clone())]);
+        .unwrap_or_else(|| vec![Name::Type(Box::new(parameter_type.clone()))]);
+    let event_types = event_names
+        .iter()
+        .filter_map(|event_name| match event_name {
+            Name::Type(event_type) => Some(event_type),
+            Name::Literal(_) => None,
+        });
+    let event_names = event_names
+        .iter()
+        .map(|event_name| event_name.to_expression(quote! {presage::Event}));
+
+    let issues = arguments.issues.map(|commands| {
+        let commands = commands
+            .iter()
+            .map(|command| command.to_expression(quote! {presage::Command}));
+        quote! {
+            fn issues(&self) -> Option<&[&'static str]> {
+                Some(&[#(#commands),*])
+            }
+        }
+    });
 
     TokenStream::from(quote! {
         #(#attrs)*
@@ -81,10 +99,18 @@ pub fn event_handler(arguments: TokenStream, handler: TokenStream) -> TokenStrea
 
         #[presage::async_trait]
         impl<#params> presage::EventHandler<#context_type, #error_type> for #handler_name #where_clause {
+            fn name(&self) -> &'static str {
+                stringify!(#handler_name)
+            }
+
             fn event_names(&self) -> &[&'static str] {
                 &[#(#event_names),*]
             }
 
+            #issues
+
+            presage::__event_schemas!(#(#event_types),*);
+
             async fn handle(&self, #context: &mut #context_type, event: #event_type) #output {
                 #event_conversion
                 #block
@@ -95,13 +121,15 @@ pub fn event_handler(arguments: TokenStream, handler: TokenStream) -> TokenStrea
 
 struct EventHandlerArguments {
     error: Option<Type>,
-    event_names: Option<Vec<EventName>>,
+    event_names: Option<Vec<Name>>,
+    issues: Option<Vec<Name>>,
 }
 
 impl Parse for EventHandlerArguments {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut error = None;
         let mut event_names = None;
+        let mut issues = None;
 
         while !input.is_empty() {
             let ident = input.parse::<Ident>()?;
@@ -112,7 +140,11 @@ impl Parse for EventHandlerArguments {
                 }
                 "events" => {
                     input.parse::<Token![=]>()?;
-                    event_names = Some(parse_event_names(input)?)
+                    event_names = Some(parse_names(input)?)
+                }
+                "issues" => {
+                    input.parse::<Token![=]>()?;
+                    issues = Some(parse_names(input)?)
                 }
                 _ => return Err(syn::Error::new_spanned(ident, "unknown argument")),
             }
@@ -121,57 +153,11 @@ impl Parse for EventHandlerArguments {
             }
         }
 
-        Ok(EventHandlerArguments { error, event_names })
-    }
-}
-
-fn parse_event_names(input: ParseStream) -> syn::Result<Vec<EventName>> {
-    if input.peek(Bracket) {
-        let content;
-        bracketed!(content in input);
-        Ok(content
-            .parse_terminated(EventName::parse, Token![,])?
-            .into_iter()
-            .collect())
-    } else {
-        Ok(vec![input.parse::<EventName>()?])
-    }
-}
-
-enum EventName {
-    Literal(LitStr),
-    Event(Type),
-}
-
-impl Parse for EventName {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let ahead = input.fork();
-        if let Ok(literal) = ahead.parse::<LitStr>() {
-            input.advance_to(&ahead);
-            if literal.value().trim().is_empty() {
-                Err(syn::Error::new_spanned(
-                    literal,
-                    "an event name must not be empty",
-                ))
-            } else {
-                Ok(EventName::Literal(literal))
-            }
-        } else if let Ok(event_type) = input.parse::<Type>() {
-            Ok(EventName::Event(event_type))
-        } else {
-            Err(input.error("string literal or event type expected"))
-        }
-    }
-}
-
-impl ToTokens for EventName {
-    fn to_tokens(&self, tokens: &mut quote::__private::TokenStream) {
-        match self {
-            Self::Literal(literal) => tokens.append_all(quote! {#literal}),
-            Self::Event(event_type) => {
-                tokens.append_all(quote! {<#event_type as presage::Event>::NAME})
-            }
-        }
+        Ok(EventHandlerArguments {
+            error,
+            event_names,
+            issues,
+        })
     }
 }
 
@@ -2,6 +2,9 @@ use proc_macro::TokenStream;
 
 mod command;
 mod event;
+mod query;
+mod saga;
+mod schema;
 pub(crate) mod utils;
 
 /// Derives the [Event](https://docs.rs/presage/latest/presage/trait.Event.html) trait.
@@ -45,11 +48,30 @@ pub fn event_handler(arguments: TokenStream, handler: TokenStream) -> TokenStrea
 ///
 /// The name of the command is the name of the type converted to kebab case (e.g., `CreateTodo`
 /// becomes `create-todo`). To specify another name, use the `#[presage(name = "name")]` attribute.
-#[proc_macro_derive(Command, attributes(presage))]
+///
+/// The [Validate](https://docs.rs/presage/latest/presage/trait.Validate.html) trait is also
+/// derived. By default, any command is valid. With the `#[presage(validate)]` attribute, the
+/// validation is generated from the rules declared on the fields of a struct with the `#[validate]`
+/// attribute: `non_empty`, `not_blank` (for strings), and `range(min = <expr>, max = <expr>)`
+/// (either bound can be omitted).
+///
+/// With the `#[presage(retryable)]` attribute, the command can be copied using its [Clone]
+/// implementation, so that its handler can be retried after a transient failure.
+#[proc_macro_derive(Command, attributes(presage, validate))]
 pub fn derive_command(command: TokenStream) -> TokenStream {
     command::derive_command::derive_command(command)
 }
 
+/// Derives the
+/// [SerializableCommand](https://docs.rs/presage/latest/presage/trait.SerializableCommand.html)
+/// trait.
+///
+/// The command must also implement `Command`, `serde::Serialize` and `serde::Deserialize`.
+#[proc_macro_derive(SerializableCommand)]
+pub fn derive_serializable_command(command: TokenStream) -> TokenStream {
+    command::derive_serializable_command::derive_serializable_command(command)
+}
+
 /// Creates an [CommandHandler](https://docs.rs/presage/latest/presage/trait.CommandHandler.html)
 /// from a function.
 ///
@@ -57,7 +79,51 @@ pub fn derive_command(command: TokenStream) -> TokenStream {
 /// `Result<presage::Commands, _>`. You can use any error type, but if it cannot be extracted from
 /// the function signature (e.g., when using a type alias for `Result`), the error type must be
 /// specified as argument of the attribute: `#[command_handler(error = MyError)]`.
+///
+/// The roles required to execute the command can be specified with the `requires` argument:
+/// `#[command_handler(requires = "todo:write")]` or `#[command_handler(requires = ["a", "b"])]`.
 #[proc_macro_attribute]
 pub fn command_handler(arguments: TokenStream, handler: TokenStream) -> TokenStream {
     command::command_handler::command_handler(arguments, handler)
 }
+
+/// Derives the [Query](https://docs.rs/presage/latest/presage/trait.Query.html) trait.
+///
+/// The name of the query is the name of the type converted to kebab case (e.g., `ListTodos`
+/// becomes `list-todos`). To specify another name, use the `#[presage(name = "name")]` attribute.
+///
+/// The type of the result of the query must be provided using the `presage` attribute:
+/// `#[presage(output = Vec<Todo>)]`.
+#[proc_macro_derive(Query, attributes(presage))]
+pub fn derive_query(query: TokenStream) -> TokenStream {
+    query::derive_query::derive_query(query)
+}
+
+/// Creates a [QueryHandler](https://docs.rs/presage/latest/presage/trait.QueryHandler.html) from
+/// a function.
+///
+/// The function must have two parameters (a shared reference to the context and the handled query)
+/// and return a `Result` of the output of the query. You can use any error type, but if it cannot
+/// be extracted from the function signature (e.g., when using a type alias for `Result`), the error
+/// type must be specified as argument of the attribute: `#[query_handler(error = MyError)]`. The
+/// result must match the output of the query, otherwise the handler does not compile.
+#[proc_macro_attribute]
+pub fn query_handler(arguments: TokenStream, handler: TokenStream) -> TokenStream {
+    query::query_handler::query_handler(arguments, handler)
+}
+
+/// Implements the [Saga](https://docs.rs/presage/latest/presage/trait.Saga.html) trait from an impl
+/// block.
+///
+/// The methods marked with `#[saga(start)]` or `#[saga(handle)]` handle events: they must take
+/// `&mut self` and an event, and return `presage::Commands`. The events handled by the methods marked
+/// with `#[saga(start)]` create a new instance of the saga. If the impl block has an
+/// `is_complete(&self) -> bool` method, it is used to know when the saga is complete.
+///
+/// The field of the events holding the correlation id must be specified as argument of the
+/// attribute: `#[saga(correlation_id = order_id)]`. By default, the name of the saga is the name of
+/// the type in kebab case, but it can be specified: `#[saga(name = "my-saga", correlation_id = id)]`.
+#[proc_macro_attribute]
+pub fn saga(arguments: TokenStream, saga: TokenStream) -> TokenStream {
+    saga::saga(arguments, saga)
+}
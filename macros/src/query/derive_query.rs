@@ -0,0 +1,114 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Attribute, Ident, Item, LitStr, Token, Type};
+
+use crate::utils::{create_str_literal_from_ident, error, has_name};
+
+pub fn derive_query(query: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(query as Item);
+
+    let QueryInfo {
+        type_name,
+        query_name,
+        output,
+    } = match item.try_into() {
+        Ok(info) => info,
+        Err(error) => return error,
+    };
+
+    TokenStream::from(quote! {
+        impl presage::Query for #type_name {
+            const NAME: &'static str = #query_name;
+            type Output = #output;
+        }
+    })
+}
+
+struct QueryInfo {
+    type_name: Ident,
+    query_name: LitStr,
+    output: Type,
+}
+
+impl QueryInfo {
+    fn try_from(type_name: Ident, attributes: &[Attribute]) -> Result<Self, TokenStream> {
+        let arguments =
+            DeriveQueryArguments::try_from(attributes).map_err(syn::Error::into_compile_error)?;
+        let query_name = arguments
+            .query_name
+            .unwrap_or_else(|| create_str_literal_from_ident(&type_name));
+        match arguments.output {
+            Some(output) => Ok(QueryInfo {
+                type_name,
+                query_name,
+                output,
+            }),
+            None => Err(error(type_name, MISSING_OUTPUT_TYPE)),
+        }
+    }
+}
+
+impl TryFrom<Item> for QueryInfo {
+    type Error = TokenStream;
+
+    fn try_from(item: Item) -> Result<Self, Self::Error> {
+        match item {
+            Item::Struct(item) => QueryInfo::try_from(item.ident, &item.attrs),
+            Item::Enum(item) => QueryInfo::try_from(item.ident, &item.attrs),
+            _ => Err(error(
+                item,
+                "Query can only be derived for a struct or an enum",
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+struct DeriveQueryArguments {
+    query_name: Option<LitStr>,
+    output: Option<Type>,
+}
+
+impl Parse for DeriveQueryArguments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut arguments = DeriveQueryArguments::default();
+
+        while !input.is_empty() {
+            let argument = input.parse::<Ident>()?;
+            match argument.to_string().as_str() {
+                "name" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.query_name = Some(input.parse()?);
+                }
+                "output" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.output = Some(input.parse()?);
+                }
+                _ => return Err(syn::Error::new_spanned(argument, "unexpected argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+impl TryFrom<&[Attribute]> for DeriveQueryArguments {
+    type Error = syn::Error;
+
+    fn try_from(attributes: &[Attribute]) -> Result<Self, Self::Error> {
+        for attribute in attributes {
+            if has_name(attribute, "presage") {
+                return attribute.parse_args();
+            }
+        }
+        Ok(DeriveQueryArguments::default())
+    }
+}
+
+const MISSING_OUTPUT_TYPE: &str = r"Cannot find the output type of the query.
+
+Help: specify the output type with `#[presage(output = <type>)]`";
@@ -0,0 +1,2 @@
+pub mod derive_query;
+pub mod query_handler;
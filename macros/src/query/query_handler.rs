@@ -0,0 +1,124 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Generics, Ident, ItemFn, ReturnType, Signature, Token, Type};
+
+use crate::utils::{error, extract_error_type, extract_input, HandlerInput};
+
+pub fn query_handler(arguments: TokenStream, handler: TokenStream) -> TokenStream {
+    let arguments = parse_macro_input!(arguments as QueryHandlerArguments);
+    let ItemFn {
+        vis,
+        sig,
+        block,
+        attrs,
+        ..
+    } = parse_macro_input!(handler as ItemFn);
+
+    let Signature {
+        asyncness,
+        ident: handler_name,
+        inputs,
+        output,
+        generics: Generics {
+            params,
+            where_clause,
+            ..
+        },
+        ..
+    } = sig;
+
+    if asyncness.is_none() {
+        return error(handler_name, "A query handler must be async");
+    }
+
+    let HandlerInput {
+        context,
+        context_type,
+        parameter,
+        parameter_type,
+    } = match extract_input(&inputs) {
+        Some(result) => result,
+        None => {
+            return error(
+                inputs,
+                r#"arguments of a query handler should match "(context: &C, query: _)""#,
+            )
+        }
+    };
+
+    let result_type = match &output {
+        ReturnType::Type(_, result_type) => result_type,
+        ReturnType::Default => {
+            return error(handler_name, "A query handler must return a `Result`");
+        }
+    };
+
+    let error_type = match arguments
+        .error
+        .as_ref()
+        .or_else(|| extract_error_type(&output))
+    {
+        Some(error_type) => error_type,
+        None => return error(handler_name, MISSING_ERROR_TYPE),
+    };
+
+    TokenStream::from(quote! {
+        #(#attrs)*
+        #[allow(non_camel_case_types)]
+        #vis struct #handler_name;
+
+        #[presage::async_trait]
+        impl<#params> presage::QueryHandler<#context_type, #error_type> for #handler_name #where_clause {
+            fn name(&self) -> &'static str {
+                stringify!(#handler_name)
+            }
+
+            fn query_name(&self) -> &'static str {
+                <#parameter_type as presage::Query>::NAME
+            }
+
+            async fn handle(
+                &self,
+                #context: &#context_type,
+                query: presage::BoxedQuery,
+            ) -> Result<presage::BoxedOutput, #error_type> {
+                let #parameter: #parameter_type = query.downcast()?;
+                let result: #result_type = async move #block.await;
+                let result: Result<<#parameter_type as presage::Query>::Output, #error_type> = result;
+                result.map(presage::BoxedOutput::new)
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct QueryHandlerArguments {
+    error: Option<Type>,
+}
+
+impl Parse for QueryHandlerArguments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut arguments = QueryHandlerArguments::default();
+
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            match ident.to_string().as_str() {
+                "error" => {
+                    input.parse::<Token![=]>()?;
+                    arguments.error = Some(input.parse()?);
+                }
+                _ => return Err(syn::Error::new_spanned(ident, "unknown argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+const MISSING_ERROR_TYPE: &str = r"Cannot find the error type.
+
+Help: specify the error type with `#[query_handler(error = <path>)] or by detailing the result type in the function signature (`Result<Output, Error>`)";
@@ -0,0 +1,181 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    parse_macro_input, FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, Receiver, Token, Type,
+};
+
+use crate::utils::{create_str_literal_from_ident, error, has_name};
+
+pub fn saga(arguments: TokenStream, saga: TokenStream) -> TokenStream {
+    let SagaArguments {
+        name,
+        correlation_id,
+    } = parse_macro_input!(arguments as SagaArguments);
+    let mut item = parse_macro_input!(saga as ItemImpl);
+
+    if let Some((_, path, _)) = &item.trait_ {
+        return error(
+            path,
+            "the saga attribute must be placed on an inherent impl block",
+        );
+    }
+    let type_name = match item.self_ty.as_ref() {
+        Type::Path(path) => match path.path.get_ident() {
+            Some(ident) => ident.clone(),
+            None => return error(path, "a saga must be a local type without generics"),
+        },
+        self_type => return error(self_type, "a saga must be a local type without generics"),
+    };
+    let correlation_id = match correlation_id {
+        Some(correlation_id) => correlation_id,
+        None => return error(&type_name, MISSING_CORRELATION_ID),
+    };
+    let saga_name = name.unwrap_or_else(|| create_str_literal_from_ident(&type_name));
+
+    let mut handlers = Vec::new();
+    let mut is_complete = false;
+    for item in item.items.iter_mut() {
+        if let ImplItem::Fn(method) = item {
+            is_complete |= method.sig.ident == "is_complete";
+            match SagaHandler::try_from(method) {
+                Ok(Some(handler)) => handlers.push(handler),
+                Ok(None) => {}
+                Err(error) => return error,
+            }
+        }
+    }
+
+    let event_types: Vec<_> = handlers.iter().map(|handler| &handler.event_type).collect();
+    let starting_event_types = handlers
+        .iter()
+        .filter(|handler| handler.start)
+        .map(|handler| &handler.event_type);
+    let methods = handlers.iter().map(|handler| &handler.method);
+    let is_complete = is_complete.then(|| {
+        quote! {
+            fn is_complete(&self) -> bool {
+                #type_name::is_complete(self)
+            }
+        }
+    });
+
+    TokenStream::from(quote! {
+        #item
+
+        impl presage::Saga for #type_name {
+            const NAME: &'static str = #saga_name;
+            const EVENTS: &'static [&'static str] = &[#(<#event_types as presage::Event>::NAME),*];
+            const STARTING_EVENTS: &'static [&'static str] =
+                &[#(<#starting_event_types as presage::Event>::NAME),*];
+
+            fn correlation_id(
+                event: &presage::SerializedEvent,
+            ) -> Result<Option<String>, presage::Error> {
+                #(
+                    if event.name() == <#event_types as presage::Event>::NAME {
+                        let event: #event_types = event.clone().deserialize()?;
+                        return Ok(Some(event.#correlation_id.to_string()));
+                    }
+                )*
+                Ok(None)
+            }
+
+            fn handle(
+                &mut self,
+                event: presage::SerializedEvent,
+            ) -> Result<presage::Commands, presage::Error> {
+                #(
+                    if event.name() == <#event_types as presage::Event>::NAME {
+                        return Ok(self.#methods(event.deserialize()?));
+                    }
+                )*
+                Ok(presage::Commands::new())
+            }
+
+            #is_complete
+        }
+    })
+}
+
+#[derive(Default)]
+struct SagaArguments {
+    name: Option<LitStr>,
+    correlation_id: Option<Ident>,
+}
+
+impl Parse for SagaArguments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut arguments = SagaArguments::default();
+
+        while !input.is_empty() {
+            let ident = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            match ident.to_string().as_str() {
+                "name" => arguments.name = Some(input.parse()?),
+                "correlation_id" => arguments.correlation_id = Some(input.parse()?),
+                _ => return Err(syn::Error::new_spanned(ident, "unknown argument")),
+            }
+            if input.peek(Token![,]) {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(arguments)
+    }
+}
+
+struct SagaHandler {
+    method: Ident,
+    event_type: Type,
+    start: bool,
+}
+
+impl SagaHandler {
+    fn try_from(method: &mut ImplItemFn) -> Result<Option<Self>, TokenStream> {
+        let mut kind = None;
+        let mut attributes = Vec::new();
+        for attribute in method.attrs.drain(..) {
+            if has_name(&attribute, "saga") {
+                let argument = attribute
+                    .parse_args::<Ident>()
+                    .map_err(syn::Error::into_compile_error)?;
+                if argument != "start" && argument != "handle" {
+                    return Err(error(argument, "expected `start` or `handle`"));
+                }
+                kind = Some(argument == "start");
+            } else {
+                attributes.push(attribute);
+            }
+        }
+        method.attrs = attributes;
+
+        let Some(start) = kind else {
+            return Ok(None);
+        };
+        let inputs = &method.sig.inputs;
+        match (inputs.first(), inputs.iter().nth(1), inputs.len()) {
+            (
+                Some(FnArg::Receiver(Receiver {
+                    reference: Some(_),
+                    mutability: Some(_),
+                    ..
+                })),
+                Some(FnArg::Typed(event)),
+                2,
+            ) => Ok(Some(SagaHandler {
+                method: method.sig.ident.clone(),
+                event_type: event.ty.as_ref().clone(),
+                start,
+            })),
+            _ => Err(error(
+                inputs,
+                r#"arguments of a saga handler should match "(&mut self, event: _)""#,
+            )),
+        }
+    }
+}
+
+const MISSING_CORRELATION_ID: &str = r"Cannot find the correlation id of the saga.
+
+Help: specify the field of the events holding the correlation id with `#[saga(correlation_id = <field>)]`";
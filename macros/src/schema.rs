@@ -0,0 +1,88 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Attribute, Fields, Item, LitStr, Visibility};
+
+use crate::utils::has_name;
+
+/// Implements `JsonSchema` for the derived type, by deriving it on a private copy of the type that
+/// only keeps the attributes relevant to the schema (`serde`, `schemars` and documentation).
+pub fn derive_json_schema(item: &Item) -> TokenStream {
+    let shadow_name = format_ident!("__PresageSchema");
+    let (type_name, shadow) = match item.clone() {
+        Item::Struct(mut item) => {
+            let type_name = item.ident;
+            item.ident = shadow_name.clone();
+            item.vis = Visibility::Inherited;
+            item.attrs = schema_attributes(item.attrs);
+            strip_fields(&mut item.fields);
+            (type_name, Item::Struct(item))
+        }
+        Item::Enum(mut item) => {
+            let type_name = item.ident;
+            item.ident = shadow_name.clone();
+            item.vis = Visibility::Inherited;
+            item.attrs = schema_attributes(item.attrs);
+            for variant in &mut item.variants {
+                variant.attrs = schema_attributes(std::mem::take(&mut variant.attrs));
+                strip_fields(&mut variant.fields);
+            }
+            (type_name, Item::Enum(item))
+        }
+        _ => return TokenStream::new(),
+    };
+    let schema_name = LitStr::new(&type_name.to_string(), type_name.span());
+
+    quote! {
+        const _: () = {
+            #[derive(presage::schemars::JsonSchema)]
+            #[schemars(crate = "presage::schemars", rename = #schema_name)]
+            #[allow(dead_code)]
+            #shadow
+
+            impl presage::schemars::JsonSchema for #type_name {
+                fn schema_name() -> ::std::borrow::Cow<'static, str> {
+                    <#shadow_name as presage::schemars::JsonSchema>::schema_name()
+                }
+
+                fn schema_id() -> ::std::borrow::Cow<'static, str> {
+                    ::std::borrow::Cow::Borrowed(concat!(module_path!(), "::", #schema_name))
+                }
+
+                fn json_schema(
+                    generator: &mut presage::schemars::SchemaGenerator,
+                ) -> presage::schemars::Schema {
+                    <#shadow_name as presage::schemars::JsonSchema>::json_schema(generator)
+                }
+            }
+        };
+    }
+}
+
+/// The implementation of the schema method of `Command` or `Event`.
+pub fn schema_method() -> TokenStream {
+    quote! {
+        fn schema(
+            generator: &mut presage::schemars::SchemaGenerator,
+        ) -> Option<presage::schemars::Schema> {
+            Some(generator.subschema_for::<Self>())
+        }
+    }
+}
+
+fn strip_fields(fields: &mut Fields) {
+    for field in fields.iter_mut() {
+        field.vis = Visibility::Inherited;
+        field.attrs = schema_attributes(std::mem::take(&mut field.attrs));
+    }
+}
+
+fn schema_attributes(attributes: Vec<Attribute>) -> Vec<Attribute> {
+    attributes
+        .into_iter()
+        .filter(|attribute| {
+            has_name(attribute, "serde")
+                || has_name(attribute, "schemars")
+                || has_name(attribute, "doc")
+        })
+        .collect()
+}
@@ -1,10 +1,15 @@
 use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
-use quote::ToTokens;
+use quote::{quote, ToTokens};
 use std::fmt::Display;
+use syn::parse::discouraged::Speculative;
+use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
-use syn::token::Comma;
-use syn::{Attribute, FnArg, GenericArgument, Ident, LitStr, Pat, PathArguments, ReturnType, Type};
+use syn::token::{Bracket, Comma};
+use syn::{
+    bracketed, Attribute, FnArg, GenericArgument, Ident, LitStr, Pat, PathArguments, ReturnType,
+    Token, Type,
+};
 
 pub fn error(tokens: impl ToTokens, message: impl Display) -> TokenStream {
     syn::Error::new_spanned(tokens, message)
@@ -43,7 +48,7 @@ pub struct HandlerInput<'a> {
     pub parameter_type: &'a Type,
 }
 
-pub fn extract_input(inputs: &Punctuated<FnArg, Comma>) -> Option<HandlerInput> {
+pub fn extract_input(inputs: &Punctuated<FnArg, Comma>) -> Option<HandlerInput<'_>> {
     if inputs.len() == 2 {
         match (&inputs[0], &inputs[1]) {
             (FnArg::Typed(context), FnArg::Typed(parameter)) => Some(HandlerInput {
@@ -72,3 +77,53 @@ pub fn create_str_literal_from_ident(type_name: &Ident) -> LitStr {
         type_name.span(),
     )
 }
+
+/// The name of a command or of an event, given either as a string literal or as the type of the
+/// command or event.
+pub enum Name {
+    Literal(LitStr),
+    Type(Box<Type>),
+}
+
+impl Name {
+    /// Converts the name into an expression, using the `NAME` constant of the given trait
+    /// (`presage::Command` or `presage::Event`) for types.
+    pub fn to_expression(&self, name_trait: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self {
+            Self::Literal(literal) => quote! {#literal},
+            Self::Type(name_type) => quote! {<#name_type as #name_trait>::NAME},
+        }
+    }
+}
+
+impl Parse for Name {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ahead = input.fork();
+        if let Ok(literal) = ahead.parse::<LitStr>() {
+            input.advance_to(&ahead);
+            if literal.value().trim().is_empty() {
+                Err(syn::Error::new_spanned(literal, "a name must not be empty"))
+            } else {
+                Ok(Name::Literal(literal))
+            }
+        } else if let Ok(name_type) = input.parse::<Type>() {
+            Ok(Name::Type(Box::new(name_type)))
+        } else {
+            Err(input.error("string literal or type expected"))
+        }
+    }
+}
+
+/// Parses a single [Name], or a list of names between brackets.
+pub fn parse_names(input: ParseStream) -> syn::Result<Vec<Name>> {
+    if input.peek(Bracket) {
+        let content;
+        bracketed!(content in input);
+        Ok(content
+            .parse_terminated(Name::parse, Token![,])?
+            .into_iter()
+            .collect())
+    } else {
+        Ok(vec![input.parse::<Name>()?])
+    }
+}
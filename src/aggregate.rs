@@ -94,7 +94,8 @@ pub trait Aggregate: Sized + Send + Sync {
 ///
 /// It always implements the [Deref] and [Clone] traits, and optionally implements the [Copy],
 /// [Debug], [Default], [Display], [Eq], [PartialEq], [Ord], [PartialOrd], [Hash], [Serialize],
-/// [Deserialize] traits if they are implemented by the wrapped type.
+/// [Deserialize] traits if they are implemented by the wrapped type. With the `schema` feature, it
+/// also implements `JsonSchema`.
 #[repr(transparent)]
 pub struct Id<A: Aggregate>(
     /// The wrapped id.
@@ -226,3 +227,26 @@ where
         self.0.hash(state)
     }
 }
+
+#[cfg(feature = "schema")]
+impl<A> schemars::JsonSchema for Id<A>
+where
+    A: Aggregate,
+    A::Id: schemars::JsonSchema,
+{
+    fn inline_schema() -> bool {
+        A::Id::inline_schema()
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        A::Id::schema_name()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        A::Id::schema_id()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        A::Id::json_schema(generator)
+    }
+}
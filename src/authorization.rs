@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+
+use crate::BoxedCommand;
+
+/// The kind of a [Principal].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PrincipalKind {
+    /// A human user.
+    User,
+    /// A service or an automated process.
+    Service,
+}
+
+/// The actor on whose behalf a [command](crate::Command) is executed.
+///
+/// A principal is attached to an execution with
+/// [CommandBus::execute_as](crate::CommandBus::execute_as), and is checked against the roles
+/// required by the [command handlers](crate::CommandHandler) and the [policies](Policy) of the
+/// command bus. The commands issued by event handlers during the
+/// execution are executed on behalf of the same principal.
+///
+/// # Example
+///
+/// ```
+/// use presage::Principal;
+///
+/// let principal = Principal::user("alice").with_role("todo:write");
+///
+/// assert!(principal.has_role("todo:write"));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Principal {
+    id: String,
+    kind: PrincipalKind,
+    roles: BTreeSet<String>,
+}
+
+impl Principal {
+    /// Creates a new principal for a user, without any role.
+    pub fn user(id: impl Into<String>) -> Self {
+        Self::new(id, PrincipalKind::User)
+    }
+
+    /// Creates a new principal for a service, without any role.
+    pub fn service(id: impl Into<String>) -> Self {
+        Self::new(id, PrincipalKind::Service)
+    }
+
+    fn new(id: impl Into<String>, kind: PrincipalKind) -> Self {
+        Self {
+            id: id.into(),
+            kind,
+            roles: BTreeSet::new(),
+        }
+    }
+
+    /// Adds a role to the principal. Takes ownership and returns the principal to allow chaining.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.insert(role.into());
+        self
+    }
+
+    /// The id of the principal.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The kind of the principal.
+    pub fn kind(&self) -> PrincipalKind {
+        self.kind
+    }
+
+    /// The roles of the principal.
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.roles.iter().map(String::as_str)
+    }
+
+    /// Returns `true` if the principal has the given role.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains(role)
+    }
+}
+
+/// Decides whether a [command](crate::Command) can be executed on behalf of a [Principal].
+///
+/// Policies are added to a [Configuration](crate::Configuration), and are checked for every command
+/// before its handler is executed. If any policy refuses the command, the
+/// [CommandBus](crate::CommandBus) returns an [Unauthorized](crate::Error::Unauthorized) error.
+///
+/// # Example
+///
+/// ```
+/// use presage::{BoxedCommand, Policy, Principal, PrincipalKind};
+///
+/// struct OnlyUsers;
+///
+/// impl Policy for OnlyUsers {
+///     fn authorize(&self, principal: Option<&Principal>, _: &BoxedCommand) -> Result<(), String> {
+///         match principal {
+///             Some(principal) if principal.kind() == PrincipalKind::User => Ok(()),
+///             _ => Err("only users can execute commands".into()),
+///         }
+///     }
+/// }
+/// ```
+pub trait Policy: Send + Sync {
+    /// Checks that the command can be executed by the principal, if any. Returns the reason of the
+    /// refusal otherwise.
+    fn authorize(
+        &self,
+        principal: Option<&Principal>,
+        command: &BoxedCommand,
+    ) -> Result<(), String>;
+}
+
+pub(crate) fn check_roles(principal: Option<&Principal>, roles: &[&str]) -> Result<(), String> {
+    match (principal, roles.is_empty()) {
+        (_, true) => Ok(()),
+        (None, false) => Err("an authenticated principal is required".into()),
+        (Some(principal), false) => match roles.iter().find(|role| !principal.has_role(role)) {
+            Some(role) => Err(format!(
+                "principal {} does not have the role {role}",
+                principal.id()
+            )),
+            None => Ok(()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        async_trait, command_handler, Command, CommandBus, Configuration, Error, EventWriter,
+        Events, ExecutionError, SerializedEvent,
+    };
+
+    #[tokio::test]
+    async fn test_required_roles() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&delete_item)
+                .command_handler(&archive_item),
+        );
+        let mut context = TestContext::default();
+
+        let anonymous = command_bus
+            .execute(&mut context, DeleteItem)
+            .await
+            .map_err(ExecutionError::into_inner);
+        let reader = command_bus
+            .execute_as(&mut context, &Principal::user("bob"), DeleteItem)
+            .await
+            .map_err(ExecutionError::into_inner);
+        let writer = command_bus
+            .execute_as(
+                &mut context,
+                &Principal::user("alice").with_role("item:write"),
+                DeleteItem,
+            )
+            .await
+            .map_err(ExecutionError::into_inner);
+
+        assert!(matches!(
+            anonymous,
+            Err(Error::Unauthorized("delete-item", _))
+        ));
+        assert!(matches!(reader, Err(Error::Unauthorized("delete-item", _))));
+        assert!(writer.is_ok());
+        assert_eq!(context.handled, vec!["delete-item"]);
+    }
+
+    #[tokio::test]
+    async fn test_policies() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&archive_item)
+                .policy(&NoServices),
+        );
+        let mut context = TestContext::default();
+
+        let user = command_bus
+            .execute_as(&mut context, &Principal::user("alice"), ArchiveItem)
+            .await
+            .map_err(ExecutionError::into_inner);
+        let service = command_bus
+            .execute_as(&mut context, &Principal::service("cleaner"), ArchiveItem)
+            .await
+            .map_err(ExecutionError::into_inner);
+
+        assert!(user.is_ok());
+        assert!(
+            matches!(service, Err(Error::Unauthorized("archive-item", reason)) if reason == "no services")
+        );
+    }
+
+    #[derive(Default)]
+    struct TestContext {
+        handled: Vec<&'static str>,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct DeleteItem;
+
+    #[command_handler(requires = "item:write")]
+    async fn delete_item(context: &mut TestContext, _: DeleteItem) -> Result<Events, Error> {
+        context.handled.push(DeleteItem::NAME);
+        Ok(Events::new())
+    }
+
+    #[derive(Command)]
+    struct ArchiveItem;
+
+    #[command_handler]
+    async fn archive_item(context: &mut TestContext, _: ArchiveItem) -> Result<Events, Error> {
+        context.handled.push(ArchiveItem::NAME);
+        Ok(Events::new())
+    }
+
+    struct NoServices;
+
+    impl Policy for NoServices {
+        fn authorize(&self, principal: Option<&Principal>, _: &BoxedCommand) -> Result<(), String> {
+            match principal.map(Principal::kind) {
+                Some(PrincipalKind::Service) => Err("no services".into()),
+                _ => Ok(()),
+            }
+        }
+    }
+}
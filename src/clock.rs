@@ -0,0 +1,64 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Provides the current time.
+///
+/// Présage never reads the system time directly, so that time-dependent behaviors (e.g.,
+/// [scheduled commands](crate::Schedule)) can be tested without waiting, using a [ManualClock].
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A [Clock] that returns the system time.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A [Clock] that only changes when it is explicitly set or advanced.
+///
+/// # Example
+///
+/// ```
+/// use presage::{Clock, ManualClock};
+/// use std::time::{Duration, SystemTime};
+///
+/// let clock = ManualClock::new(SystemTime::UNIX_EPOCH);
+/// clock.advance(Duration::from_secs(60));
+///
+/// assert_eq!(clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    /// Creates a new clock, set at the given time.
+    pub const fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(|error| error.into_inner()) = now;
+    }
+
+    /// Moves the current time forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(|error| error.into_inner()) += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
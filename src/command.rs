@@ -1,12 +1,16 @@
 use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::any::{type_name, Any};
 use std::fmt::Debug;
 
-use crate::{Error, Events};
+use crate::{Error, Events, Validate, ValidationError};
 
 /// A request to modify the system.
 ///
-/// A corresponding [command handler](CommandHandler) must be defined.
+/// A corresponding [command handler](CommandHandler) must be defined. A command must also implement
+/// [Validate], which is used to check the command before it is handled.
 ///
 /// # Associated constant
 ///
@@ -15,7 +19,21 @@ use crate::{Error, Events};
 /// # Example
 ///
 /// ```
-/// # use presage::Id;
+/// # use presage::{Aggregate, AggregateEvent, Id};
+/// #
+/// # #[derive(AggregateEvent, serde::Serialize, serde::Deserialize)]
+/// # #[presage(Todo)]
+/// # pub struct TodoCreated { #[id] id: Id<Todo> }
+/// # pub struct Todo { id: Id<Todo> }
+/// # impl Aggregate for Todo {
+/// #     type Id = u64;
+/// #     type CreationEvent = TodoCreated;
+/// #     type UpdateEvent = TodoCreated;
+/// #     type DeletionEvent = TodoCreated;
+/// #     fn id(&self) -> Id<Self> { self.id }
+/// #     fn new(event: TodoCreated) -> Self { Self { id: event.id } }
+/// #     fn apply(&mut self, _: TodoCreated) {}
+/// # }
 /// #
 /// #[derive(Debug)]
 /// pub struct CreateTodo {
@@ -26,10 +44,98 @@ use crate::{Error, Events};
 /// impl presage::Command for CreateTodo {
 ///     const NAME: &'static str = "create-todo";
 /// }
+///
+/// impl presage::Validate for CreateTodo {}
 /// ```
-pub trait Command: Sized + Send + Sync + 'static {
+pub trait Command: Validate + Sized + Send + Sync + 'static {
     /// The name of the command. Must be unique.
     const NAME: &'static str;
+
+    /// Creates a copy of the command, used to execute its handler again when it fails with a
+    /// transient error (see [RetryPolicy](crate::RetryPolicy)). By default, a command cannot be
+    /// copied, and its handler is never retried.
+    fn try_clone(&self) -> Option<Self> {
+        None
+    }
+
+    /// The JSON schema of the command, added to the
+    /// [schema of the configuration](crate::Configuration::schema). By default, a command has no
+    /// schema. When deriving [Command], the schema is derived with `#[presage(schema)]`.
+    #[cfg(feature = "schema")]
+    fn schema(_generator: &mut schemars::SchemaGenerator) -> Option<schemars::Schema> {
+        None
+    }
+}
+
+/// A [Command] that can be serialized, so that it can be sent from outside the process, e.g., as
+/// JSON over HTTP or through a queue.
+///
+/// A serializable command must be registered in the [Configuration](crate::Configuration) with
+/// [serializable_command()](crate::Configuration::serializable_command), so that the
+/// [CommandBus](crate::CommandBus) can decode it from a [SerializedCommand].
+///
+/// # Example
+///
+/// ```
+/// #[derive(presage::Command, serde::Serialize, serde::Deserialize)]
+/// pub struct CreateTodo {
+///     pub name: String,
+/// }
+///
+/// impl presage::SerializableCommand for CreateTodo {}
+/// ```
+///
+/// With the `derive` feature, the trait can also be derived: `#[derive(SerializableCommand)]`.
+pub trait SerializableCommand: Command + Serialize + DeserializeOwned {
+    /// Converts the command into a [SerializedCommand].
+    fn to_serialized(&self) -> Result<SerializedCommand, Error> {
+        Ok(SerializedCommand {
+            name: Self::NAME.to_string(),
+            payload: serde_json::to_value(self)
+                .map_err(|error| Error::CommandSerializationError(Self::NAME, error))?,
+        })
+    }
+}
+
+/// A [SerializableCommand] in its serialized form, with the name of the command and its payload.
+///
+/// It can itself be serialized and deserialized, e.g., from `{"name": "create-todo", "payload":
+/// {"name": "Buy milk"}}`, and executed with
+/// [CommandBus::execute_serialized()](crate::CommandBus::execute_serialized).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializedCommand {
+    name: String,
+    payload: Value,
+}
+
+impl SerializedCommand {
+    /// Creates a serialized command from the name of a command and its payload.
+    pub fn new(name: impl Into<String>, payload: Value) -> Self {
+        Self {
+            name: name.into(),
+            payload,
+        }
+    }
+
+    /// The name of the serialized command.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The payload of the serialized command.
+    pub fn payload(&self) -> &Value {
+        &self.payload
+    }
+
+    pub(crate) fn into_payload(self) -> Value {
+        self.payload
+    }
+
+    /// Tries to convert the serialized command into a concrete [SerializableCommand].
+    pub fn into_command<C: SerializableCommand>(self) -> Result<C, Error> {
+        serde_json::from_value(self.payload)
+            .map_err(|error| Error::CommandSerializationError(C::NAME, error))
+    }
 }
 
 /// A command that has been boxed to be dispatched.
@@ -39,6 +145,8 @@ pub trait Command: Sized + Send + Sync + 'static {
 pub struct BoxedCommand {
     name: &'static str,
     command: Box<dyn Any + Send + Sync>,
+    validate: fn(&(dyn Any + Send + Sync)) -> Result<(), ValidationError>,
+    try_clone: fn(&(dyn Any + Send + Sync)) -> Option<BoxedCommand>,
 }
 
 impl BoxedCommand {
@@ -47,6 +155,21 @@ impl BoxedCommand {
         self.name
     }
 
+    /// Validates the boxed command, using its [Validate] implementation.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        (self.validate)(self.command.as_ref())
+    }
+
+    /// Creates a copy of the boxed command, if its [Command] implementation allows it.
+    pub fn try_clone(&self) -> Option<BoxedCommand> {
+        (self.try_clone)(self.command.as_ref())
+    }
+
+    /// Returns a reference to the boxed command if it is of type `C`.
+    pub fn downcast_ref<C: Command>(&self) -> Option<&C> {
+        self.command.downcast_ref()
+    }
+
     /// Tries to downcast the boxed command to a concrete [Command] implementation.
     pub fn downcast<C: Command>(self) -> Result<C, Error> {
         self.command
@@ -61,6 +184,13 @@ impl<C: Command> From<C> for BoxedCommand {
         BoxedCommand {
             name: C::NAME,
             command: Box::new(command),
+            validate: |command| command.downcast_ref().map_or(Ok(()), C::validate),
+            try_clone: |command| {
+                command
+                    .downcast_ref::<C>()
+                    .and_then(C::try_clone)
+                    .map(BoxedCommand::from)
+            },
         }
     }
 }
@@ -113,9 +243,51 @@ macro_rules! commands {
 /// * `E` - the type of errors returned if the handler fails
 #[async_trait]
 pub trait CommandHandler<C, E>: Send + Sync {
+    /// The name of the handler. Defaults to the name of the implementing type.
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
     /// The name of the handled command.
     fn command_name(&self) -> &'static str;
 
+    /// The roles that the [principal](crate::Principal) executing the command must have. By
+    /// default, no role is required.
+    fn requires(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// The names of the events that the handler may return, as declared by the handler, e.g., with
+    /// `#[command_handler(emits = [TodoCreated])]`. Used to render the [flow](crate::EventFlow) of
+    /// a configuration, and to [verify](crate::Configuration::verify_declarations) the handler. An
+    /// empty declaration states that the handler returns no event. By default, nothing is declared.
+    fn emits(&self) -> Option<&[&'static str]> {
+        None
+    }
+
+    /// The JSON schema of the handled command (see [Command::schema]). Implemented by the
+    /// `command_handler` macro. By default, the command has no schema.
+    #[cfg(feature = "schema")]
+    fn command_schema(
+        &self,
+        _generator: &mut schemars::SchemaGenerator,
+    ) -> Option<schemars::Schema> {
+        None
+    }
+
+    /// The JSON schemas of the [declared events](Self::emits) that have one (see [Event::schema]),
+    /// with their names. Implemented by the `command_handler` macro, for the events declared by
+    /// type. By default, no schema is returned.
+    ///
+    /// [Event::schema]: crate::Event::schema
+    #[cfg(feature = "schema")]
+    fn event_schemas(
+        &self,
+        _generator: &mut schemars::SchemaGenerator,
+    ) -> Vec<(&'static str, schemars::Schema)> {
+        Vec::new()
+    }
+
     /// Executes a command, with the given context.
     async fn handle(&self, context: &mut C, command: BoxedCommand) -> Result<Events, E>;
 }
@@ -1,15 +1,26 @@
 use async_trait::async_trait;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
+use crate::authorization::check_roles;
+use crate::configuration::{Compensate, Decode, HandlerKey};
+use crate::dead_letter::DeadLetters;
+use crate::instrumentation::{self, Span, Timer};
+use crate::middleware::WriteFn;
+#[cfg(feature = "subscriptions")]
+use crate::subscription::{Subscription, Subscriptions, DEFAULT_CAPACITY};
 use crate::{
-    BoxedCommand, Command, CommandHandler, Configuration, Error, EventHandler, SerializedEvent,
+    BoxedCommand, Clock, Command, CommandHandler, Commands, Compensation, Configuration,
+    DeadLetter, Error, EventHandler, EventMiddleware, EventPublisher, Events, ExecutionError,
+    HandleNext, Policy, Principal, RetryPolicy, ScheduleStore, SerializedCommand, SerializedEvent,
+    WriteNext,
 };
 
 /// Executes a command and handles issued [events](crate::Event).
 ///
 /// Takes a context and a command to execute. The resulting events are persisted, then any matching
 /// event handler is executed. Those event handlers can return new commands which are also executed.
-/// The process continues as long as new commands are issued.
+/// The process continues as long as new commands are issued. The persistence and the handling of
+/// events can be intercepted by [event middlewares](EventMiddleware).
 ///
 /// Can be created using [new()](CommandBus::new) or the [Default] implementation.
 pub struct CommandBus<C, E>
@@ -19,6 +30,19 @@ where
 {
     command_handlers: HashMap<&'static str, &'static dyn CommandHandler<C, E>>,
     event_handlers: HashMap<&'static str, Vec<&'static dyn EventHandler<C, E>>>,
+    event_middlewares: Vec<&'static dyn EventMiddleware<C, E>>,
+    policies: Vec<&'static dyn Policy>,
+    retry_policy: Option<RetryPolicy<E>>,
+    handler_retry_policies: HashMap<HandlerKey, RetryPolicy<E>>,
+    dead_letters: Option<DeadLetters<E>>,
+    dead_letter_handlers: HashSet<&'static str>,
+    compensations: HashMap<&'static str, Compensate>,
+    event_publishers: Vec<&'static dyn EventPublisher>,
+    topics: HashMap<&'static str, &'static str>,
+    serializable_commands: HashMap<&'static str, Decode>,
+    verify_declarations: bool,
+    #[cfg(feature = "subscriptions")]
+    subscriptions: Subscriptions,
 }
 
 impl<C, E> Default for CommandBus<C, E> {
@@ -33,6 +57,19 @@ impl<C, E> CommandBus<C, E> {
         Self {
             command_handlers: Default::default(),
             event_handlers: Default::default(),
+            event_middlewares: Default::default(),
+            policies: Default::default(),
+            retry_policy: None,
+            handler_retry_policies: Default::default(),
+            dead_letters: None,
+            dead_letter_handlers: Default::default(),
+            compensations: Default::default(),
+            event_publishers: Default::default(),
+            topics: Default::default(),
+            serializable_commands: Default::default(),
+            verify_declarations: false,
+            #[cfg(feature = "subscriptions")]
+            subscriptions: Subscriptions::new(DEFAULT_CAPACITY),
         }
     }
 
@@ -41,7 +78,22 @@ impl<C, E> CommandBus<C, E> {
     ///
     /// # Example
     /// ```
-    /// let command_bus = presage::CommandBus::new()
+    /// # use presage::{command_handler, event_handler, Command, Commands, Event, Events};
+    /// #
+    /// # #[derive(Command)]
+    /// # struct SomeCommand;
+    /// # #[derive(Event, serde::Serialize, serde::Deserialize)]
+    /// # struct SomeEvent;
+    /// # #[command_handler]
+    /// # async fn some_command_handler(_: &mut (), _: SomeCommand) -> Result<Events, presage::Error> {
+    /// #     Ok(Events::new())
+    /// # }
+    /// # #[event_handler]
+    /// # async fn some_event_handler(_: &mut (), _: SomeEvent) -> Result<Commands, presage::Error> {
+    /// #     Ok(Commands::new())
+    /// # }
+    /// #
+    /// let command_bus: presage::CommandBus<(), presage::Error> = presage::CommandBus::new()
     ///     .configure(
     ///         presage::Configuration::new()
     ///             .event_handler(&some_event_handler)
@@ -51,8 +103,35 @@ impl<C, E> CommandBus<C, E> {
     pub fn configure(mut self, configuration: Configuration<C, E>) -> Self {
         self.event_handlers.extend(configuration.event_handlers);
         self.command_handlers.extend(configuration.command_handlers);
+        self.event_middlewares
+            .extend(configuration.event_middlewares);
+        self.policies.extend(configuration.policies);
+        self.retry_policy = configuration.retry_policy.or(self.retry_policy);
+        self.handler_retry_policies
+            .extend(configuration.handler_retry_policies);
+        self.dead_letters = configuration.dead_letters.or(self.dead_letters);
+        self.dead_letter_handlers
+            .extend(configuration.dead_letter_handlers);
+        self.compensations.extend(configuration.compensations);
+        self.event_publishers.extend(configuration.event_publishers);
+        self.topics.extend(configuration.topics);
+        self.serializable_commands
+            .extend(configuration.serializable_commands);
+        self.verify_declarations |= configuration.verify_declarations;
+        #[cfg(feature = "subscriptions")]
+        if let Some(capacity) = configuration.subscription_capacity {
+            self.subscriptions = Subscriptions::new(capacity);
+        }
         self
     }
+
+    /// Subscribes to the events written by the command bus, or by any of its clones. The returned
+    /// [Subscription] is an async stream, which receives the events written after its creation,
+    /// once they have been successfully written.
+    #[cfg(feature = "subscriptions")]
+    pub fn subscribe(&self) -> Subscription {
+        self.subscriptions.subscribe()
+    }
 }
 
 impl<C, E> CommandBus<C, E>
@@ -60,27 +139,447 @@ where
     C: EventWriter<Error = E>,
     E: From<Error>,
 {
-    /// Executes a [command](Command) with the provided context. If the execution returns any event,
-    /// they are persisted using [event writers](EventWriter), then the corresponding
+    const WRITE: WriteFn<C, E> = |context, event| context.write(event);
+
+    /// Executes a [command](Command) with the provided context. The command is first
+    /// [validated](crate::Validate), then handled. If the execution returns any event, they are
+    /// persisted using [event writers](EventWriter), then the corresponding
     /// [event handlers](EventHandler) are executed. If new commands are returned, they are also
     /// executed. The process continues until no more events and commands are issued.
-    pub async fn execute<T>(&self, context: &mut C, command: T) -> Result<(), E>
+    ///
+    /// The first failure stops the execution, and is returned as an [ExecutionError] recording
+    /// where it happened. The [compensating commands](Configuration::compensation) of the commands
+    /// that were already handled are then executed in reverse order, and their outcomes are added
+    /// to the error.
+    ///
+    /// The command is executed without any [principal](Principal): it is rejected if its handler
+    /// requires roles or if a [policy](crate::Policy) refuses it.
+    pub async fn execute<T>(&self, context: &mut C, command: T) -> Result<(), ExecutionError<E>>
     where
         T: Command,
     {
-        let mut commands: VecDeque<BoxedCommand> = VecDeque::from([command.into()]);
-        while let Some(command) = commands.pop_front() {
-            let events = self
-                .get_command_handler(command.name())?
-                .handle(context, command)
+        self.execute_commands(
+            context,
+            None,
+            VecDeque::from([(command.into(), Vec::new())]),
+        )
+        .await
+    }
+
+    /// Executes a boxed command, like [execute()](CommandBus::execute).
+    #[cfg(feature = "proptest")]
+    pub(crate) async fn execute_boxed(
+        &self,
+        context: &mut C,
+        command: BoxedCommand,
+    ) -> Result<(), ExecutionError<E>> {
+        self.execute_commands(context, None, VecDeque::from([(command, Vec::new())]))
+            .await
+    }
+
+    /// Executes a [command](Command) on behalf of a [principal](Principal). Before being handled,
+    /// each command of the execution is checked against the roles required by its handler and the
+    /// [policies](crate::Policy) of the command bus. Otherwise, behaves like
+    /// [execute()](CommandBus::execute).
+    pub async fn execute_as<T>(
+        &self,
+        context: &mut C,
+        principal: &Principal,
+        command: T,
+    ) -> Result<(), ExecutionError<E>>
+    where
+        T: Command,
+    {
+        self.execute_commands(
+            context,
+            Some(principal),
+            VecDeque::from([(command.into(), Vec::new())]),
+        )
+        .await
+    }
+
+    /// Decodes a [serialized command](SerializedCommand) and executes it, like
+    /// [execute()](CommandBus::execute). The command must have been registered with
+    /// [Configuration::serializable_command()].
+    pub async fn execute_serialized(
+        &self,
+        context: &mut C,
+        command: SerializedCommand,
+    ) -> Result<(), ExecutionError<E>> {
+        let command = self.decode(command)?;
+        self.execute_commands(context, None, VecDeque::from([(command, Vec::new())]))
+            .await
+    }
+
+    /// Decodes a [serialized command](SerializedCommand) and executes it on behalf of a
+    /// [principal](Principal), like [execute_as()](CommandBus::execute_as). The command must have
+    /// been registered with [Configuration::serializable_command()].
+    pub async fn execute_serialized_as(
+        &self,
+        context: &mut C,
+        principal: &Principal,
+        command: SerializedCommand,
+    ) -> Result<(), ExecutionError<E>> {
+        let command = self.decode(command)?;
+        self.execute_commands(
+            context,
+            Some(principal),
+            VecDeque::from([(command, Vec::new())]),
+        )
+        .await
+    }
+
+    /// The names of the registered [serializable commands](crate::SerializableCommand).
+    #[cfg(feature = "axum")]
+    pub(crate) fn serializable_command_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.serializable_commands.keys().copied()
+    }
+
+    fn decode(&self, command: SerializedCommand) -> Result<BoxedCommand, ExecutionError<E>> {
+        let Some((name, decode)) = self.serializable_commands.get_key_value(command.name()) else {
+            let error = Error::UnknownCommand(command.name().to_string());
+            return Err(ExecutionError::new(error.into(), None, &[]));
+        };
+        decode(command.into_payload())
+            .map_err(|error| ExecutionError::new(error.into(), Some(name), &[name]))
+    }
+
+    /// Re-drives the [dead letters](DeadLetter) of the dead letter store through their handler.
+    /// Each dead letter that is successfully handled is removed from the store, and the commands
+    /// returned by the handler are executed without any [principal](Principal). Dead letters that
+    /// fail again, or whose handler is not configured anymore, are left in the store.
+    ///
+    /// Returns the number of re-driven dead letters.
+    pub async fn redrive(&self, context: &mut C) -> Result<usize, ExecutionError<E>> {
+        let Some(dead_letters) = self.dead_letters else {
+            return Ok(0);
+        };
+        let failed = |error: Error| ExecutionError::new(error.into(), None, &[]);
+        let mut redriven = 0;
+        for letter in dead_letters.store.list().await.map_err(failed)? {
+            let Some((event, handler)) = self.find_dead_letter_handler(&letter) else {
+                continue;
+            };
+            if let Ok(commands) = self.handle_event_with(context, handler, &event).await {
+                dead_letters
+                    .store
+                    .remove(letter.id())
+                    .await
+                    .map_err(|error| failed(error).at_event(event.name()))?;
+                redriven += 1;
+                let path = vec![event.name()];
+                self.execute_commands(
+                    context,
+                    None,
+                    commands
+                        .into_iter()
+                        .map(|command| (command, path.clone()))
+                        .collect(),
+                )
                 .await?;
-            for event in events {
-                commands.extend(self.handle_event(context, event).await?);
+            }
+        }
+        Ok(redriven)
+    }
+
+    /// Executes the [scheduled commands](crate::Schedule) that are due, according to the
+    /// [Clock] of the context. Each scheduled command is decoded from its
+    /// [serialized form](SerializedCommand), and executed like with
+    /// [execute()](CommandBus::execute), without any [principal](Principal). Only the commands
+    /// that are due when the method is called are executed, not the ones they schedule.
+    ///
+    /// A command is [completed](ScheduleStore::complete), and removed from the [ScheduleStore],
+    /// once it has been successfully executed. The first failure stops the execution: the failing
+    /// command, which cannot be decoded or whose execution failed, stays in the store along with
+    /// the remaining ones, and is executed again by the next call.
+    ///
+    /// Returns the number of executed commands.
+    pub async fn run_due(&self, context: &mut C) -> Result<usize, ExecutionError<E>>
+    where
+        C: ScheduleStore + Clock,
+        E: From<<C as ScheduleStore>::Error>,
+    {
+        let store_failed =
+            |error: <C as ScheduleStore>::Error| ExecutionError::new(error.into(), None, &[]);
+        let now = context.now();
+        let due = context.due(now).await.map_err(store_failed)?;
+        let mut executed = 0;
+        for scheduled in due {
+            let command = self.decode(scheduled.command().clone())?;
+            let commands = VecDeque::from([(command, Vec::new())]);
+            self.execute_commands(context, None, commands).await?;
+            context
+                .complete(scheduled.id())
+                .await
+                .map_err(store_failed)?;
+            executed += 1;
+        }
+        Ok(executed)
+    }
+
+    fn find_dead_letter_handler(
+        &self,
+        letter: &DeadLetter,
+    ) -> Option<(SerializedEvent, &'static dyn EventHandler<C, E>)> {
+        let (name, handlers) = self.event_handlers.get_key_value(letter.event())?;
+        let handler = handlers
+            .iter()
+            .find(|handler| handler.key() == letter.handler_key())?;
+        let event = SerializedEvent::new(name, letter.value().clone(), letter.metadata().clone());
+        Some((event, *handler))
+    }
+
+    async fn execute_commands(
+        &self,
+        context: &mut C,
+        principal: Option<&Principal>,
+        commands: VecDeque<(BoxedCommand, Vec<&'static str>)>,
+    ) -> Result<(), ExecutionError<E>> {
+        let span = Span::execution(commands.front().map(|(command, _)| command.name()));
+        span.instrument(async {
+            let mut compensations = Vec::new();
+            match self
+                .run_commands(context, principal, commands, &mut compensations)
+                .await
+            {
+                Ok(()) => Ok(()),
+                Err(error) => {
+                    let compensations = self.compensate(context, principal, compensations).await;
+                    let error = error.compensated(compensations);
+                    span.record_error(&error);
+                    Err(error)
+                }
+            }
+        })
+        .await
+    }
+
+    async fn compensate(
+        &self,
+        context: &mut C,
+        principal: Option<&Principal>,
+        compensations: Vec<BoxedCommand>,
+    ) -> Vec<Compensation<E>> {
+        let mut outcomes = Vec::new();
+        for command in compensations.into_iter().rev() {
+            let command_name = command.name();
+            let commands = VecDeque::from([(command, Vec::new())]);
+            let result = self
+                .run_commands(context, principal, commands, &mut Vec::new())
+                .await;
+            outcomes.push(Compensation::new(command_name, result.err()));
+        }
+        outcomes
+    }
+
+    async fn run_commands(
+        &self,
+        context: &mut C,
+        principal: Option<&Principal>,
+        mut commands: VecDeque<(BoxedCommand, Vec<&'static str>)>,
+        compensations: &mut Vec<BoxedCommand>,
+    ) -> Result<(), ExecutionError<E>> {
+        let mut executed = 0;
+        let result = loop {
+            let Some((command, mut path)) = commands.pop_front() else {
+                break Ok(());
+            };
+            let command_name = command.name();
+            path.push(command_name);
+            let span = Span::command(command_name, &path);
+            let result = span
+                .instrument(self.run_command(
+                    context,
+                    principal,
+                    command,
+                    &path,
+                    &mut commands,
+                    compensations,
+                ))
+                .await;
+            executed += 1;
+            instrumentation::record_command(command_name, result.is_ok());
+            if result.is_err() {
+                span.record_failure();
+                break result;
+            }
+        };
+        instrumentation::record_cascade(executed);
+        result
+    }
+
+    async fn run_command(
+        &self,
+        context: &mut C,
+        principal: Option<&Principal>,
+        command: BoxedCommand,
+        path: &[&'static str],
+        commands: &mut VecDeque<(BoxedCommand, Vec<&'static str>)>,
+        compensations: &mut Vec<BoxedCommand>,
+    ) -> Result<(), ExecutionError<E>> {
+        let command_name = command.name();
+        let failed = |error: E| ExecutionError::new(error, Some(command_name), path);
+        let handler = self
+            .get_command_handler(command_name)
+            .map_err(|error| failed(error.into()))?;
+        Span::current().record_handler(handler.name());
+        self.authorize(principal, handler, &command)
+            .map_err(|error| failed(error.into()))?;
+        command
+            .validate()
+            .map_err(|error| failed(Error::InvalidCommand(command_name, error).into()))?;
+        // From now on, the handler runs, so failures are attributed to it
+        let failed = |error: E| failed(error).in_handler(handler.name());
+        let mut compensation = self
+            .compensations
+            .get(command_name)
+            .and_then(|compensate| compensate(&command));
+        let timer = Timer::start();
+        let events = self.handle_command(context, handler, command).await;
+        timer.record_command_handler(handler.name());
+        let events = events.map_err(failed)?;
+        self.verify_declared(
+            handler.name(),
+            handler.emits(),
+            events.0.iter().map(SerializedEvent::name),
+            Error::UndeclaredEvent,
+        )
+        .map_err(|error| failed(error.into()))?;
+        Span::current().record_events(events.0.len());
+        if events.0.is_empty() {
+            compensations.extend(compensation.take());
+        }
+        for mut event in events {
+            let event_name = event.name();
+            let path = [path, &[event_name]].concat();
+            let failed = |error: E| {
+                ExecutionError::new(error, Some(command_name), &path).at_event(event_name)
+            };
+            self.write_event(context, &mut event)
+                .await
+                .map_err(failed)?;
+            // The command only needs to be compensated once one of its events has been written
+            compensations.extend(compensation.take());
+            instrumentation::record_event_written(event_name);
+            self.publish(&event)
+                .await
+                .map_err(|error| failed(error.into()))?;
+            #[cfg(feature = "subscriptions")]
+            self.subscriptions.publish(&event);
+            for handler in self.event_handlers.get(event_name).into_iter().flatten() {
+                let span = Span::event_handler(event_name, handler.name());
+                let timer = Timer::start();
+                let result = span
+                    .instrument(self.handle_event_with(context, *handler, &event))
+                    .await
+                    .inspect_err(|_| span.record_failure());
+                timer.record_event_handler(event_name, handler.name());
+                let issued = match result {
+                    Ok(issued) => {
+                        self.verify_declared(
+                            handler.name(),
+                            handler.issues(),
+                            issued.0.iter().map(BoxedCommand::name),
+                            Error::UndeclaredCommand,
+                        )
+                        .map_err(|error| failed(error.into()).in_handler(handler.name()))?;
+                        issued
+                    }
+                    Err(error) => self
+                        .dead_letter(*handler, &event, error)
+                        .await
+                        .map_err(|error| failed(error).in_handler(handler.name()))?,
+                };
+                commands.extend(issued.into_iter().map(|command| (command, path.clone())));
             }
         }
         Ok(())
     }
 
+    async fn dead_letter(
+        &self,
+        handler: &dyn EventHandler<C, E>,
+        event: &SerializedEvent,
+        error: E,
+    ) -> Result<Commands, E> {
+        match self.dead_letters {
+            Some(dead_letters) if self.dead_letter_handlers.contains(handler.key()) => {
+                let letter = DeadLetter::new(
+                    handler.key(),
+                    handler.name(),
+                    event,
+                    (dead_letters.describe)(&error),
+                );
+                dead_letters.store.add(letter).await?;
+                Ok(Commands::new())
+            }
+            _ => Err(error),
+        }
+    }
+
+    fn authorize(
+        &self,
+        principal: Option<&Principal>,
+        handler: &dyn CommandHandler<C, E>,
+        command: &BoxedCommand,
+    ) -> Result<(), Error> {
+        check_roles(principal, handler.requires())
+            .and_then(|_| {
+                self.policies
+                    .iter()
+                    .try_for_each(|policy| policy.authorize(principal, command))
+            })
+            .map_err(|reason| Error::Unauthorized(command.name(), reason))
+    }
+
+    async fn handle_command(
+        &self,
+        context: &mut C,
+        handler: &dyn CommandHandler<C, E>,
+        mut command: BoxedCommand,
+    ) -> Result<Events, E> {
+        let policy = self.get_retry_policy(HandlerKey::command(handler));
+        let mut attempt = 1;
+        loop {
+            let copy = policy.and_then(|_| command.try_clone());
+            match (handler.handle(context, command).await, policy, copy) {
+                (Err(error), Some(policy), Some(copy)) if policy.should_retry(attempt, &error) => {
+                    policy.wait(attempt).await;
+                    command = copy;
+                    attempt += 1;
+                }
+                (result, _, _) => return result,
+            }
+        }
+    }
+
+    /// Checks, in debug builds, that a handler only produced the events or commands it declares.
+    /// Handlers without declaration are not checked.
+    fn verify_declared(
+        &self,
+        handler: &'static str,
+        declared: Option<&[&'static str]>,
+        produced: impl IntoIterator<Item = &'static str>,
+        undeclared: fn(&'static str, &'static str) -> Error,
+    ) -> Result<(), Error> {
+        let Some(declared) = declared else {
+            return Ok(());
+        };
+        if !cfg!(debug_assertions) || !self.verify_declarations {
+            return Ok(());
+        }
+        match produced.into_iter().find(|name| !declared.contains(name)) {
+            Some(name) => Err(undeclared(handler, name)),
+            None => Ok(()),
+        }
+    }
+
+    fn get_retry_policy(&self, handler: HandlerKey) -> Option<&RetryPolicy<E>> {
+        self.handler_retry_policies
+            .get(&handler)
+            .or(self.retry_policy.as_ref())
+    }
+
     fn get_command_handler(
         &self,
         command_name: &'static str,
@@ -88,22 +587,52 @@ where
         self.command_handlers
             .get(command_name)
             .ok_or(Error::MissingCommandHandler(command_name))
-            .map(|handler| *handler)
+            .copied()
     }
 
-    async fn handle_event(
+    async fn write_event(&self, context: &mut C, event: &mut SerializedEvent) -> Result<(), E> {
+        WriteNext::new(&self.event_middlewares, Self::WRITE)
+            .run(context, event)
+            .await
+    }
+
+    /// Publishes an event with the configured [event publishers](EventPublisher), on the topic of
+    /// the event, like after it has been written.
+    ///
+    /// Events are forwarded at most once: when publishing an event fails, the event is already
+    /// written, but the execution stops and the event is neither handled nor published again. Once
+    /// the publisher has recovered, the event can be read back from where it was written and
+    /// published again with this method.
+    pub async fn publish(&self, event: &SerializedEvent) -> Result<(), Error> {
+        let topic = self
+            .topics
+            .get(event.name())
+            .copied()
+            .unwrap_or(event.name());
+        for publisher in &self.event_publishers {
+            publisher.publish(topic, event).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_event_with(
         &self,
         context: &mut C,
-        event: SerializedEvent,
-    ) -> Result<Vec<BoxedCommand>, E> {
-        context.write(&event).await?;
-        let mut commands = Vec::new();
-        if let Some(handlers) = self.event_handlers.get(event.name()) {
-            for handler in handlers {
-                commands.extend(handler.handle(context, &event).await?);
+        handler: &'static dyn EventHandler<C, E>,
+        event: &SerializedEvent,
+    ) -> Result<Commands, E> {
+        let policy = self.get_retry_policy(HandlerKey::event(handler));
+        let mut attempt = 1;
+        loop {
+            let next = HandleNext::new(&self.event_middlewares, handler);
+            match (next.run(context, event).await, policy) {
+                (Err(error), Some(policy)) if policy.should_retry(attempt, &error) => {
+                    policy.wait(attempt).await;
+                    attempt += 1;
+                }
+                (result, _) => return result,
             }
         }
-        Ok(commands)
     }
 }
 
@@ -116,6 +645,19 @@ where
         Self {
             command_handlers: self.command_handlers.clone(),
             event_handlers: self.event_handlers.clone(),
+            event_middlewares: self.event_middlewares.clone(),
+            policies: self.policies.clone(),
+            retry_policy: self.retry_policy,
+            handler_retry_policies: self.handler_retry_policies.clone(),
+            dead_letters: self.dead_letters,
+            dead_letter_handlers: self.dead_letter_handlers.clone(),
+            compensations: self.compensations.clone(),
+            event_publishers: self.event_publishers.clone(),
+            topics: self.topics.clone(),
+            serializable_commands: self.serializable_commands.clone(),
+            verify_declarations: self.verify_declarations,
+            #[cfg(feature = "subscriptions")]
+            subscriptions: self.subscriptions.clone(),
         }
     }
 }
@@ -136,3 +678,244 @@ pub trait EventWriter: Send + Sync {
     /// Writes an event.
     async fn write(&mut self, event: &SerializedEvent) -> Result<(), Self::Error>;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{command_handler, commands, event_handler, events, Event, SerializableCommand};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_compensations_are_executed_in_reverse_order() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .command_handler(&delete_item)
+                .event_handler(&on_item_created)
+                .compensation(|command: &CreateItem| DeleteItem(command.0)),
+        );
+        let mut context = TestContext::default();
+
+        let error = command_bus
+            .execute(&mut context, CreateItem(1))
+            .await
+            .unwrap_err();
+
+        assert_eq!(context.items, Vec::<u32>::new());
+        assert_eq!(context.deleted, vec![2, 1]);
+        assert_eq!(error.command(), Some("create-item"));
+        assert_eq!(
+            error.path(),
+            [
+                "create-item",
+                "item-created",
+                "create-item",
+                "item-created",
+                "create-item"
+            ]
+        );
+        let compensations: Vec<_> = error
+            .compensations()
+            .iter()
+            .map(|compensation| (compensation.command(), compensation.is_success()))
+            .collect();
+        assert_eq!(
+            compensations,
+            vec![("delete-item", true), ("delete-item", true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_compensations_are_reported() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .event_handler(&on_item_created)
+                .compensation(|command: &CreateItem| DeleteItem(command.0)),
+        );
+        let mut context = TestContext::default();
+
+        let error = command_bus
+            .execute(&mut context, CreateItem(2))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.compensations().len(), 1);
+        assert!(matches!(
+            error.compensations()[0].error().map(ExecutionError::error),
+            Some(Error::MissingCommandHandler("delete-item"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serialized_commands_are_decoded_and_executed() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&delete_item)
+                .serializable_command::<DeleteItem>(),
+        );
+        let mut context = TestContext::default();
+        let command: SerializedCommand =
+            serde_json::from_value(json!({"name": "delete-item", "payload": 3})).unwrap();
+
+        command_bus
+            .execute_serialized(&mut context, command)
+            .await
+            .unwrap();
+
+        assert_eq!(context.deleted, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_serialized_commands_are_rejected() {
+        let command_bus =
+            CommandBus::new().configure(Configuration::new().command_handler(&delete_item));
+        let command = DeleteItem(3).to_serialized().unwrap();
+
+        let error = command_bus
+            .execute_serialized(&mut TestContext::default(), command)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error.error(), Error::UnknownCommand(name) if name == "delete-item"));
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    async fn test_undeclared_events_are_reported() {
+        let configuration = Configuration::new().command_handler(&audit_item);
+        let command_bus = CommandBus::new().configure(configuration.verify_declarations());
+
+        let error = command_bus
+            .execute(&mut TestContext::default(), AuditItem(1))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.error(),
+            Error::UndeclaredEvent("audit_item", "item-audited")
+        ));
+        assert_eq!(error.handler(), Some("audit_item"));
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    async fn test_empty_declarations_are_verified() {
+        let configuration = Configuration::new().command_handler(&inspect_item);
+        let command_bus = CommandBus::new().configure(configuration.verify_declarations());
+
+        let error = command_bus
+            .execute(&mut TestContext::default(), InspectItem(1))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.error(),
+            Error::UndeclaredEvent("inspect_item", "item-audited")
+        ));
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    async fn test_undeclared_commands_are_reported() {
+        let configuration = Configuration::new()
+            .command_handler(&create_item)
+            .command_handler(&delete_item)
+            .event_handler(&replace_item)
+            .verify_declarations();
+        let command_bus = CommandBus::new().configure(configuration);
+
+        let error = command_bus
+            .execute(&mut TestContext::default(), CreateItem(1))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.error(),
+            Error::UndeclaredCommand("replace_item", "create-item")
+        ));
+        assert_eq!(error.path(), ["create-item", "item-created"]);
+    }
+
+    #[tokio::test]
+    async fn test_declarations_are_not_verified_by_default() {
+        let command_bus =
+            CommandBus::new().configure(Configuration::new().command_handler(&audit_item));
+
+        let result = command_bus
+            .execute(&mut TestContext::default(), AuditItem(1))
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[derive(Default)]
+    struct TestContext {
+        items: Vec<u32>,
+        deleted: Vec<u32>,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CreateItem(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated(u32);
+
+    #[command_handler]
+    async fn create_item(context: &mut TestContext, command: CreateItem) -> Result<Events, Error> {
+        if command.0 > 2 {
+            return Err(Error::MissingCommandHandler("storage"));
+        }
+        context.items.push(command.0);
+        Ok(events!(ItemCreated(command.0)))
+    }
+
+    #[event_handler]
+    async fn on_item_created(_: &mut TestContext, event: ItemCreated) -> Result<Commands, Error> {
+        Ok(commands!(CreateItem(event.0 + 1)))
+    }
+
+    #[derive(Command, crate::SerializableCommand, Serialize, Deserialize)]
+    struct DeleteItem(u32);
+
+    #[command_handler]
+    async fn delete_item(context: &mut TestContext, command: DeleteItem) -> Result<Events, Error> {
+        context.items.retain(|item| *item != command.0);
+        context.deleted.push(command.0);
+        Ok(Events::new())
+    }
+
+    #[derive(Command)]
+    struct AuditItem(u32);
+
+    #[derive(Command)]
+    struct InspectItem(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemAudited(u32);
+
+    #[command_handler(emits = [ItemCreated])]
+    async fn audit_item(_: &mut TestContext, command: AuditItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated(command.0), ItemAudited(command.0)))
+    }
+
+    #[command_handler(emits = [])]
+    async fn inspect_item(_: &mut TestContext, command: InspectItem) -> Result<Events, Error> {
+        Ok(events!(ItemAudited(command.0)))
+    }
+
+    #[event_handler(issues = [DeleteItem])]
+    async fn replace_item(_: &mut TestContext, event: ItemCreated) -> Result<Commands, Error> {
+        Ok(commands!(DeleteItem(event.0), CreateItem(event.0 + 1)))
+    }
+}
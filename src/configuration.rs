@@ -1,10 +1,41 @@
+use serde_json::Value;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::ops::{Add, AddAssign};
+use std::sync::Arc;
 
-use crate::{CommandHandler, EventHandler};
+use crate::dead_letter::DeadLetters;
+use crate::saga::SagaHandler;
+use crate::schedule::{CancelScheduleHandler, ScheduleHandler};
+use crate::{
+    BoxedCommand, Clock, Command, CommandHandler, DeadLetterStore, Error, Event, EventFlow,
+    EventHandler, EventMiddleware, EventPublisher, Policy, QueryHandler, RetryPolicy, Saga,
+    SagaStore, ScheduleStore, SerializableCommand,
+};
 
-/// A configuration for a [CommandBus](crate::CommandBus).
+pub(crate) type Compensate = Arc<dyn Fn(&BoxedCommand) -> Option<BoxedCommand> + Send + Sync>;
+pub(crate) type Decode = fn(Value) -> Result<BoxedCommand, Error>;
+
+/// Identifies a handler in a configuration. Handler names are not unique, so command handlers are
+/// identified by their command, and event handlers by their [key](EventHandler::key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum HandlerKey {
+    Command(&'static str),
+    Event(&'static str),
+}
+
+impl HandlerKey {
+    pub(crate) fn command<C, E>(handler: &dyn CommandHandler<C, E>) -> Self {
+        Self::Command(handler.command_name())
+    }
+
+    pub(crate) fn event<C, E>(handler: &dyn EventHandler<C, E>) -> Self {
+        Self::Event(handler.key())
+    }
+}
+
+/// A configuration for a [CommandBus](crate::CommandBus) and a [QueryBus](crate::QueryBus).
 ///
 /// Implements [Add] and [AddAssign] for composition of multiple configurations.
 pub struct Configuration<C, E>
@@ -14,6 +45,20 @@ where
 {
     pub(crate) command_handlers: HashMap<&'static str, &'static dyn CommandHandler<C, E>>,
     pub(crate) event_handlers: HashMap<&'static str, Vec<&'static dyn EventHandler<C, E>>>,
+    pub(crate) event_middlewares: Vec<&'static dyn EventMiddleware<C, E>>,
+    pub(crate) policies: Vec<&'static dyn Policy>,
+    pub(crate) retry_policy: Option<RetryPolicy<E>>,
+    pub(crate) handler_retry_policies: HashMap<HandlerKey, RetryPolicy<E>>,
+    pub(crate) dead_letters: Option<DeadLetters<E>>,
+    pub(crate) dead_letter_handlers: HashSet<&'static str>,
+    pub(crate) compensations: HashMap<&'static str, Compensate>,
+    pub(crate) query_handlers: HashMap<&'static str, &'static dyn QueryHandler<C, E>>,
+    pub(crate) event_publishers: Vec<&'static dyn EventPublisher>,
+    pub(crate) topics: HashMap<&'static str, &'static str>,
+    pub(crate) serializable_commands: HashMap<&'static str, Decode>,
+    pub(crate) verify_declarations: bool,
+    #[cfg(feature = "subscriptions")]
+    pub(crate) subscription_capacity: Option<usize>,
 }
 
 impl<C, E> Configuration<C, E> {
@@ -22,6 +67,20 @@ impl<C, E> Configuration<C, E> {
         Self {
             command_handlers: Default::default(),
             event_handlers: Default::default(),
+            event_middlewares: Default::default(),
+            policies: Default::default(),
+            retry_policy: None,
+            handler_retry_policies: Default::default(),
+            dead_letters: None,
+            dead_letter_handlers: Default::default(),
+            compensations: Default::default(),
+            query_handlers: Default::default(),
+            event_publishers: Default::default(),
+            topics: Default::default(),
+            serializable_commands: Default::default(),
+            verify_declarations: false,
+            #[cfg(feature = "subscriptions")]
+            subscription_capacity: None,
         }
     }
 
@@ -44,6 +103,263 @@ impl<C, E> Configuration<C, E> {
             .insert(handler.command_name(), handler);
         self
     }
+
+    /// Adds a new query handler to the configuration. Query handlers are used by the
+    /// [QueryBus](crate::QueryBus) only. Takes ownership and returns the configuration to allow
+    /// chaining.
+    pub fn query_handler(mut self, handler: &'static dyn QueryHandler<C, E>) -> Self {
+        self.query_handlers.insert(handler.query_name(), handler);
+        self
+    }
+
+    /// Adds a new event middleware to the configuration. Middlewares are run in the order in which
+    /// they are added. Takes ownership and returns the configuration to allow chaining.
+    pub fn event_middleware(mut self, middleware: &'static dyn EventMiddleware<C, E>) -> Self {
+        self.event_middlewares.push(middleware);
+        self
+    }
+
+    /// Adds a new authorization policy to the configuration. Takes ownership and returns the
+    /// configuration to allow chaining.
+    pub fn policy(mut self, policy: &'static dyn Policy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// Sets the retry policy applied to every handler that does not have its own policy. Takes
+    /// ownership and returns the configuration to allow chaining.
+    pub fn retry_policy(mut self, policy: RetryPolicy<E>) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Adds a new event handler to the configuration, with its own retry policy. Takes ownership
+    /// and returns the configuration to allow chaining.
+    pub fn event_handler_with_retry(
+        mut self,
+        handler: &'static dyn EventHandler<C, E>,
+        policy: RetryPolicy<E>,
+    ) -> Self {
+        self.handler_retry_policies
+            .insert(HandlerKey::event(handler), policy);
+        self.event_handler(handler)
+    }
+
+    /// Adds a new command handler to the configuration, with its own retry policy. Takes ownership
+    /// and returns the configuration to allow chaining.
+    pub fn command_handler_with_retry(
+        mut self,
+        handler: &'static dyn CommandHandler<C, E>,
+        policy: RetryPolicy<E>,
+    ) -> Self {
+        self.handler_retry_policies
+            .insert(HandlerKey::command(handler), policy);
+        self.command_handler(handler)
+    }
+
+    /// Adds a new [saga](Saga) to the configuration. Its state is persisted by the context, which
+    /// must implement [SagaStore]. Takes ownership and returns the configuration to allow chaining.
+    pub fn saga<S>(self) -> Self
+    where
+        S: Saga,
+        C: SagaStore + Send,
+        E: From<Error> + From<C::Error>,
+    {
+        self.event_handler(&SagaHandler::<S>::HANDLER)
+    }
+
+    /// Registers the compensating command of a command. When an execution fails, the compensating
+    /// commands of the commands that were successfully handled are executed in reverse order, to
+    /// undo their effects. A command returning events is only compensated once its first event has
+    /// been written. Takes ownership and returns the configuration to allow chaining.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use presage::{Command, Configuration};
+    /// # #[derive(Command)]
+    /// # struct CreateTodo { id: u32 }
+    /// # #[derive(Command)]
+    /// # struct DeleteTodo { id: u32 }
+    /// # let configuration: Configuration<(), presage::Error> =
+    /// Configuration::new().compensation(|command: &CreateTodo| DeleteTodo { id: command.id })
+    /// # ;
+    /// ```
+    pub fn compensation<T, U>(mut self, compensate: fn(&T) -> U) -> Self
+    where
+        T: Command,
+        U: Command,
+    {
+        self.compensations.insert(
+            T::NAME,
+            Arc::new(move |command| command.downcast_ref().map(compensate).map(Into::into)),
+        );
+        self
+    }
+
+    /// Registers a [serializable command](SerializableCommand), so that the command bus can decode
+    /// and execute it from a [SerializedCommand](crate::SerializedCommand). Takes ownership and
+    /// returns the configuration to allow chaining.
+    pub fn serializable_command<T: SerializableCommand>(mut self) -> Self {
+        self.serializable_commands.insert(T::NAME, |payload| {
+            serde_json::from_value::<T>(payload)
+                .map(BoxedCommand::from)
+                .map_err(|error| Error::CommandSerializationError(T::NAME, error))
+        });
+        self
+    }
+
+    /// Adds the handlers of [Schedule](crate::Schedule) and [CancelSchedule](crate::CancelSchedule)
+    /// to the configuration. The scheduled commands are kept by the context, which must implement
+    /// [ScheduleStore] and [Clock](crate::Clock). Takes ownership and returns the configuration to
+    /// allow chaining.
+    pub fn scheduler(self) -> Self
+    where
+        C: ScheduleStore + Clock,
+        E: From<Error> + From<C::Error>,
+    {
+        self.command_handler(&ScheduleHandler)
+            .command_handler(&CancelScheduleHandler)
+    }
+
+    /// Sets the store in which the events that non-critical event handlers failed to handle are
+    /// saved. Takes ownership and returns the configuration to allow chaining.
+    pub fn dead_letter_store(mut self, store: &'static dyn DeadLetterStore) -> Self
+    where
+        E: Display,
+    {
+        self.dead_letters = Some(DeadLetters::new(store));
+        self
+    }
+
+    /// Adds a new event publisher to the configuration. Every written event is published by every
+    /// publisher. Takes ownership and returns the configuration to allow chaining.
+    pub fn event_publisher(mut self, publisher: &'static dyn EventPublisher) -> Self {
+        self.event_publishers.push(publisher);
+        self
+    }
+
+    /// Sets the topic on which the events of type `T` are published by the
+    /// [event publishers](EventPublisher). By default, an event is published on a topic named after
+    /// the event. Takes ownership and returns the configuration to allow chaining.
+    pub fn topic<T: Event>(mut self, topic: &'static str) -> Self {
+        self.topics.insert(T::NAME, topic);
+        self
+    }
+
+    /// Sets the capacity of the channel of the [subscriptions](crate::Subscription) of the command
+    /// bus, i.e., the number of events a subscription can lag behind before missing events. The
+    /// default capacity is 1024. Takes ownership and returns the configuration to allow chaining.
+    ///
+    /// # Panics
+    ///
+    /// The command bus panics when configured with a capacity of 0.
+    #[cfg(feature = "subscriptions")]
+    pub fn subscription_capacity(mut self, capacity: usize) -> Self {
+        self.subscription_capacity = Some(capacity);
+        self
+    }
+
+    /// Enables the verification of the declarations of the handlers, in debug builds only. A
+    /// command handler that [declares](CommandHandler::emits) the events it emits then fails the
+    /// execution with [Error::UndeclaredEvent] when it returns any other event, and an event
+    /// handler that [declares](EventHandler::issues) the commands it issues fails it with
+    /// [Error::UndeclaredCommand]. Handlers without declarations are not verified. Takes ownership
+    /// and returns the configuration to allow chaining.
+    pub fn verify_declarations(mut self) -> Self {
+        self.verify_declarations = true;
+        self
+    }
+
+    /// Adds a new non-critical event handler to the configuration. If the handler fails (after
+    /// retries, if any), the event is saved in the dead letter store and the execution continues.
+    /// Without a dead letter store, the failure stops the execution. Takes ownership and returns
+    /// the configuration to allow chaining.
+    pub fn event_handler_with_dead_letters(
+        mut self,
+        handler: &'static dyn EventHandler<C, E>,
+    ) -> Self {
+        self.dead_letter_handlers.insert(handler.key());
+        self.event_handler(handler)
+    }
+
+    /// Describes the [flow](EventFlow) of commands and events between the handlers of the
+    /// configuration, e.g., to render it as a diagram.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use presage::{command_handler, Command, Configuration, Error, Events};
+    /// # #[derive(Command)]
+    /// # struct CreateTodo;
+    /// # #[command_handler]
+    /// # async fn create_todo(_: &mut (), _: CreateTodo) -> Result<Events, Error> {
+    /// #     Ok(Events::new())
+    /// # }
+    /// let configuration = Configuration::new().command_handler(&create_todo);
+    ///
+    /// let mermaid = configuration.event_flow().to_mermaid();
+    /// assert!(mermaid.contains(r#"["create-todo"]"#));
+    /// ```
+    pub fn event_flow(&self) -> EventFlow {
+        EventFlow::new(self)
+    }
+
+    /// Produces a JSON schema document describing the commands and events of the configuration.
+    ///
+    /// The document contains the schemas of the commands of the command handlers under `commands`,
+    /// and the schemas of the events of the event handlers, or [emitted](CommandHandler::emits) by
+    /// the command handlers, under `events`, both keyed by their names. The definitions referenced
+    /// by those schemas are gathered under `$defs`. Commands and events without a schema (see
+    /// [Command::schema] and [Event::schema]) are omitted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use presage::{command_handler, Command, Configuration, Error, Events};
+    /// #[derive(Command)]
+    /// #[presage(schema)]
+    /// struct CreateTodo {
+    ///     name: String,
+    /// }
+    ///
+    /// #[command_handler]
+    /// async fn create_todo(_: &mut (), _: CreateTodo) -> Result<Events, Error> {
+    ///     Ok(Events::new())
+    /// }
+    ///
+    /// let schema = Configuration::new().command_handler(&create_todo).schema();
+    ///
+    /// assert_eq!(schema["commands"]["create-todo"]["$ref"], "#/$defs/CreateTodo");
+    /// assert_eq!(schema["$defs"]["CreateTodo"]["required"][0], "name");
+    /// ```
+    #[cfg(feature = "schema")]
+    pub fn schema(&self) -> Value {
+        let mut generator = schemars::SchemaGenerator::default();
+        let mut commands = serde_json::Map::new();
+        for handler in self.command_handlers.values() {
+            if let Some(schema) = handler.command_schema(&mut generator) {
+                commands.insert(handler.command_name().to_string(), schema.to_value());
+            }
+        }
+        let mut events = serde_json::Map::new();
+        for handler in self.command_handlers.values() {
+            for (name, schema) in handler.event_schemas(&mut generator) {
+                events.insert(name.to_string(), schema.to_value());
+            }
+        }
+        for handler in self.event_handlers.values().flatten() {
+            for (name, schema) in handler.event_schemas(&mut generator) {
+                events.insert(name.to_string(), schema.to_value());
+            }
+        }
+        serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "commands": commands,
+            "events": events,
+            "$defs": generator.take_definitions(true),
+        })
+    }
 }
 
 impl<C, E> Default for Configuration<C, E> {
@@ -74,9 +390,27 @@ impl<C, E> AddAssign for Configuration<C, E> {
             }
         }
         self.command_handlers.extend(rhs.command_handlers);
+        self.event_middlewares.extend(rhs.event_middlewares);
+        self.policies.extend(rhs.policies);
+        self.retry_policy = rhs.retry_policy.or(self.retry_policy);
+        self.handler_retry_policies
+            .extend(rhs.handler_retry_policies);
+        self.dead_letters = rhs.dead_letters.or(self.dead_letters);
+        self.dead_letter_handlers.extend(rhs.dead_letter_handlers);
+        self.compensations.extend(rhs.compensations);
+        self.query_handlers.extend(rhs.query_handlers);
+        self.event_publishers.extend(rhs.event_publishers);
+        self.topics.extend(rhs.topics);
+        self.serializable_commands.extend(rhs.serializable_commands);
+        self.verify_declarations = self.verify_declarations || rhs.verify_declarations;
+        #[cfg(feature = "subscriptions")]
+        {
+            self.subscription_capacity = rhs.subscription_capacity.or(self.subscription_capacity);
+        }
     }
 }
 
+#[cfg(test)]
 mod test {
     use super::*;
     use crate::{commands, Commands, SerializedEvent};
@@ -117,4 +451,114 @@ mod test {
             Ok(commands!())
         }
     }
+
+    #[cfg(feature = "schema")]
+    mod schema {
+        use super::*;
+        use crate::{
+            command_handler, event_handler, events, Aggregate, AggregateEvent, Events, Id,
+        };
+
+        #[test]
+        fn test_schema() {
+            let configuration: Configuration<Library, Error> = Configuration::new()
+                .command_handler(&borrow_book)
+                .event_handler(&notify_librarian);
+
+            let schema = configuration.schema();
+
+            assert_eq!(
+                schema["commands"]["borrow-book"]["$ref"],
+                "#/$defs/BorrowBook"
+            );
+            assert_eq!(schema["events"]["book-event"]["$ref"], "#/$defs/BookEvent");
+            assert_eq!(
+                schema["$defs"]["BorrowBook"]["properties"]["bookId"]["type"],
+                "integer"
+            );
+            assert_eq!(
+                schema["$defs"]["BookEvent"]["oneOf"]
+                    .as_array()
+                    .unwrap()
+                    .len(),
+                2
+            );
+        }
+
+        struct Library;
+
+        struct Book {
+            id: Id<Book>,
+        }
+
+        impl Aggregate for Book {
+            type Id = u32;
+            type CreationEvent = BookEvent;
+            type UpdateEvent = BookEvent;
+            type DeletionEvent = BookEvent;
+
+            fn id(&self) -> Id<Self> {
+                self.id
+            }
+
+            fn new(event: BookEvent) -> Self {
+                Self { id: event.id() }
+            }
+
+            fn apply(&mut self, _: BookEvent) {}
+        }
+
+        #[derive(crate::Command, serde::Serialize, serde::Deserialize)]
+        #[presage(schema)]
+        #[serde(rename_all = "camelCase")]
+        struct BorrowBook {
+            book_id: Id<Book>,
+        }
+
+        #[derive(AggregateEvent, serde::Serialize, serde::Deserialize)]
+        #[presage(Book, id = id, schema)]
+        enum BookEvent {
+            Borrowed { id: Id<Book> },
+            Returned { id: Id<Book> },
+        }
+
+        #[command_handler]
+        async fn borrow_book(_: &mut Library, _: BorrowBook) -> Result<Events, Error> {
+            Ok(Events::new())
+        }
+
+        #[event_handler]
+        async fn notify_librarian(_: &mut Library, _: BookEvent) -> Result<Commands, Error> {
+            Ok(commands!())
+        }
+
+        #[test]
+        fn test_schema_includes_emitted_events() {
+            let configuration: Configuration<Library, Error> =
+                Configuration::new().command_handler(&declare_lost);
+
+            let schema = configuration.schema();
+
+            assert_eq!(schema["events"]["book-lost"]["$ref"], "#/$defs/BookLost");
+            assert_eq!(
+                schema["$defs"]["BookLost"]["properties"]["bookId"]["type"],
+                "integer"
+            );
+        }
+
+        #[derive(crate::Command)]
+        struct DeclareLost(Id<Book>);
+
+        #[derive(crate::Event, serde::Serialize, serde::Deserialize)]
+        #[presage(schema)]
+        #[serde(rename_all = "camelCase")]
+        struct BookLost {
+            book_id: Id<Book>,
+        }
+
+        #[command_handler(emits = BookLost)]
+        async fn declare_lost(_: &mut Library, command: DeclareLost) -> Result<Events, Error> {
+            Ok(events!(BookLost { book_id: command.0 }))
+        }
+    }
 }
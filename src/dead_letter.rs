@@ -0,0 +1,419 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{Error, SerializedEvent};
+
+/// An event that a non-critical [event handler](crate::EventHandler) failed to handle.
+///
+/// Dead letters are created by the [CommandBus](crate::CommandBus) for the handlers added with
+/// [Configuration::event_handler_with_dead_letters](crate::Configuration::event_handler_with_dead_letters),
+/// and are saved in a [DeadLetterStore]. They can later be re-driven through their handler with
+/// [CommandBus::redrive](crate::CommandBus::redrive).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    id: u64,
+    handler_key: String,
+    handler: String,
+    event: String,
+    value: Value,
+    metadata: Map<String, Value>,
+    error: String,
+}
+
+impl DeadLetter {
+    pub(crate) fn new(
+        handler_key: &str,
+        handler: &str,
+        event: &SerializedEvent,
+        error: String,
+    ) -> Self {
+        Self {
+            id: 0,
+            handler_key: handler_key.to_string(),
+            handler: handler.to_string(),
+            event: event.name().to_string(),
+            value: event.value().clone(),
+            metadata: event.metadata().clone(),
+            error,
+        }
+    }
+
+    /// Sets the id of the dead letter. Used by [stores](DeadLetterStore) when adding a dead letter.
+    /// Takes ownership and returns the dead letter to allow chaining.
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// The id of the dead letter, unique within its store.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The [key](crate::EventHandler::key) of the event handler that failed, which identifies the
+    /// handler when the dead letter is re-driven.
+    pub fn handler_key(&self) -> &str {
+        &self.handler_key
+    }
+
+    /// The name of the event handler that failed.
+    pub fn handler(&self) -> &str {
+        &self.handler
+    }
+
+    /// The name of the event that could not be handled.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    /// The serialized value of the event.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// The metadata of the event.
+    pub fn metadata(&self) -> &Map<String, Value> {
+        &self.metadata
+    }
+
+    /// The description of the error returned by the event handler.
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+/// Saves the [dead letters](DeadLetter) of a [CommandBus](crate::CommandBus).
+///
+/// Présage provides an [in-memory store](InMemoryDeadLetterStore) and a
+/// [file-backed store](FileDeadLetterStore).
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    /// Adds a dead letter to the store, and returns the id assigned to it.
+    async fn add(&self, letter: DeadLetter) -> Result<u64, Error>;
+
+    /// Lists the dead letters of the store, in the order in which they were added.
+    async fn list(&self) -> Result<Vec<DeadLetter>, Error>;
+
+    /// Removes the dead letter with the given id. Does nothing if there is no such dead letter.
+    async fn remove(&self, id: u64) -> Result<(), Error>;
+}
+
+/// A [DeadLetterStore] that keeps dead letters in memory.
+///
+/// # Example
+///
+/// ```
+/// use presage::InMemoryDeadLetterStore;
+///
+/// static DEAD_LETTERS: InMemoryDeadLetterStore = InMemoryDeadLetterStore::new();
+/// ```
+#[derive(Debug, Default)]
+pub struct InMemoryDeadLetterStore {
+    letters: Mutex<(u64, Vec<DeadLetter>)>,
+}
+
+impl InMemoryDeadLetterStore {
+    /// Creates a new empty store.
+    pub const fn new() -> Self {
+        Self {
+            letters: Mutex::new((0, Vec::new())),
+        }
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn add(&self, letter: DeadLetter) -> Result<u64, Error> {
+        let mut letters = self.letters.lock().map_err(store_error)?;
+        letters.0 += 1;
+        let id = letters.0;
+        letters.1.push(letter.with_id(id));
+        Ok(id)
+    }
+
+    async fn list(&self) -> Result<Vec<DeadLetter>, Error> {
+        Ok(self.letters.lock().map_err(store_error)?.1.clone())
+    }
+
+    async fn remove(&self, id: u64) -> Result<(), Error> {
+        let mut letters = self.letters.lock().map_err(store_error)?;
+        letters.1.retain(|letter| letter.id != id);
+        Ok(())
+    }
+}
+
+/// A [DeadLetterStore] that keeps dead letters in a file, with one JSON document per line.
+///
+/// The file is created when the first dead letter is added. The last assigned id is kept in a
+/// second file, with the same path and the `.last-id` suffix, so that ids are never reused. When a
+/// dead letter is removed, the remaining ones are written to a temporary file, which then replaces
+/// the store, so that a failure cannot lose the dead letters.
+///
+/// The store uses blocking I/O from `std::fs`, within the async methods of [DeadLetterStore], while
+/// holding a lock: it is meant for low volumes of dead letters, and can stall the executor of an
+/// async runtime when the file system is slow.
+#[derive(Debug)]
+pub struct FileDeadLetterStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileDeadLetterStore {
+    /// Creates a new store backed by the file at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<DeadLetter>, Error> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(store_error(error)),
+        };
+        let mut letters = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(store_error)?;
+            if !line.trim().is_empty() {
+                letters.push(serde_json::from_str(&line).map_err(store_error)?);
+            }
+        }
+        Ok(letters)
+    }
+
+    fn write(&self, file: &mut File, letter: &DeadLetter) -> Result<(), Error> {
+        let line = serde_json::to_string(letter).map_err(store_error)?;
+        writeln!(file, "{line}").map_err(store_error)
+    }
+
+    fn next_id(&self) -> Result<u64, Error> {
+        let path = self.sibling(".last-id");
+        let last_id = match fs::read_to_string(&path) {
+            Ok(last_id) => last_id.trim().parse().map_err(store_error)?,
+            // Without counter, fall back on the ids of the stored dead letters
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                self.read()?.iter().map(DeadLetter::id).max().unwrap_or(0)
+            }
+            Err(error) => return Err(store_error(error)),
+        };
+        let id = last_id + 1;
+        self.replace(&path, |file| write!(file, "{id}").map_err(store_error))?;
+        Ok(id)
+    }
+
+    /// Atomically replaces the file at `path`, by writing a temporary file and renaming it.
+    fn replace(
+        &self,
+        path: &Path,
+        write: impl FnOnce(&mut File) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let temporary = self.sibling(".tmp");
+        let mut file = File::create(&temporary).map_err(store_error)?;
+        write(&mut file)?;
+        file.sync_all().map_err(store_error)?;
+        fs::rename(&temporary, path).map_err(store_error)
+    }
+
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        path.into()
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for FileDeadLetterStore {
+    async fn add(&self, letter: DeadLetter) -> Result<u64, Error> {
+        let _lock = self.lock.lock().map_err(store_error)?;
+        let id = self.next_id()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(store_error)?;
+        self.write(&mut file, &letter.with_id(id))?;
+        Ok(id)
+    }
+
+    async fn list(&self) -> Result<Vec<DeadLetter>, Error> {
+        let _lock = self.lock.lock().map_err(store_error)?;
+        self.read()
+    }
+
+    async fn remove(&self, id: u64) -> Result<(), Error> {
+        let _lock = self.lock.lock().map_err(store_error)?;
+        let letters = self.read()?;
+        self.replace(&self.path, |file| {
+            letters
+                .iter()
+                .filter(|letter| letter.id != id)
+                .try_for_each(|letter| self.write(file, letter))
+        })
+    }
+}
+
+fn store_error(error: impl Display) -> Error {
+    Error::DeadLetterStoreError(error.to_string())
+}
+
+/// The dead letter store of a [CommandBus](crate::CommandBus), with the way to describe errors.
+pub(crate) struct DeadLetters<E> {
+    pub(crate) store: &'static dyn DeadLetterStore,
+    pub(crate) describe: fn(&E) -> String,
+}
+
+impl<E> DeadLetters<E> {
+    pub(crate) fn new(store: &'static dyn DeadLetterStore) -> Self
+    where
+        E: Display,
+    {
+        Self {
+            store,
+            describe: |error| error.to_string(),
+        }
+    }
+}
+
+impl<E> Clone for DeadLetters<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for DeadLetters<E> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, commands, event_handler, events, Command, CommandBus, Commands,
+        Configuration, Event, EventHandler, EventWriter, Events,
+    };
+
+    #[tokio::test]
+    async fn test_failing_handlers_are_dead_lettered_and_redriven() {
+        static DEAD_LETTERS: InMemoryDeadLetterStore = InMemoryDeadLetterStore::new();
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .dead_letter_store(&DEAD_LETTERS)
+                .command_handler(&create_item)
+                .command_handler(&notify_owner)
+                .event_handler_with_dead_letters(&on_item_created),
+        );
+        let mut context = TestContext {
+            mailer_available: false,
+            notified: 0,
+        };
+
+        command_bus.execute(&mut context, CreateItem).await.unwrap();
+
+        let letters = DEAD_LETTERS.list().await.unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].handler(), "on_item_created");
+        assert_eq!(letters[0].handler_key(), on_item_created.key());
+        assert_eq!(letters[0].event(), "item-created");
+        assert_eq!(
+            letters[0].error(),
+            "Missing command handler for command mailer"
+        );
+
+        context.mailer_available = true;
+        let redriven = command_bus.redrive(&mut context).await.unwrap();
+
+        assert_eq!(redriven, 1);
+        assert_eq!(context.notified, 1);
+        assert!(DEAD_LETTERS.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_critical_handlers_stop_the_execution() {
+        static DEAD_LETTERS: InMemoryDeadLetterStore = InMemoryDeadLetterStore::new();
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .dead_letter_store(&DEAD_LETTERS)
+                .command_handler(&create_item)
+                .event_handler(&on_item_created),
+        );
+        let mut context = TestContext {
+            mailer_available: false,
+            notified: 0,
+        };
+
+        let result = command_bus.execute(&mut context, CreateItem).await;
+
+        assert!(result.is_err());
+        assert!(DEAD_LETTERS.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let path =
+            std::env::temp_dir().join(format!("presage-dead-letters-{}", std::process::id()));
+        let store = FileDeadLetterStore::new(&path);
+        let event = ItemCreated.serialize().unwrap();
+        let letter = |error: &str| DeadLetter::new("key", "handler", &event, error.into());
+
+        let first = store.add(letter("first")).await.unwrap();
+        let second = store.add(letter("second")).await.unwrap();
+        store.remove(second).await.unwrap();
+        let third = store.add(letter("third")).await.unwrap();
+        store.remove(first).await.unwrap();
+        let letters = store.list().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(store.sibling(".last-id")).unwrap();
+
+        assert_eq!((first, second, third), (1, 2, 3));
+        assert_eq!(letters, vec![letter("third").with_id(3)]);
+    }
+
+    struct TestContext {
+        mailer_available: bool,
+        notified: u32,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CreateItem;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated;
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated))
+    }
+
+    #[event_handler]
+    async fn on_item_created(context: &mut TestContext, _: ItemCreated) -> Result<Commands, Error> {
+        if context.mailer_available {
+            Ok(commands!(NotifyOwner))
+        } else {
+            Err(Error::MissingCommandHandler("mailer"))
+        }
+    }
+
+    #[derive(Command)]
+    struct NotifyOwner;
+
+    #[command_handler]
+    async fn notify_owner(context: &mut TestContext, _: NotifyOwner) -> Result<Events, Error> {
+        context.notified += 1;
+        Ok(Events::new())
+    }
+}
@@ -1,14 +1,380 @@
+use std::fmt::{Display, Formatter};
+
+use crate::ValidationError;
+
 /// Errors that can occur during the execution of a command by a [CommandBus](crate::CommandBus).
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// A command handler failed to downcast a [BoxedCommand](crate::BoxedCommand).
     #[error("Could not downcast command to type {0}")]
     CommandDowncastError(&'static str),
-    /// An error occurred when serializing or deserializing an [Event](crate::Event).
-    #[error("Serialization error: {0}")]
-    SerializationError(#[from] serde_json::Error),
+    /// An error occurred when serializing or deserializing the [Event](crate::Event) with the given
+    /// name.
+    #[error("Could not serialize or deserialize event {0}: {1}")]
+    SerializationError(&'static str, #[source] serde_json::Error),
     /// A [Command](crate::Command) was dispatched but the command bus does not have a corresponding
     /// [CommandHandler](crate::CommandHandler).
     #[error("Missing command handler for command {0}")]
     MissingCommandHandler(&'static str),
+    /// A [Command](crate::Command) was rejected by its [validation](crate::Validate).
+    #[error("Invalid command {0}: {1}")]
+    InvalidCommand(&'static str, ValidationError),
+    /// A [Command](crate::Command) was not authorized for the [Principal](crate::Principal)
+    /// executing it.
+    #[error("Command {0} is not authorized: {1}")]
+    Unauthorized(&'static str, String),
+    /// A [DeadLetterStore](crate::DeadLetterStore) failed to add, list, or remove dead letters.
+    #[error("Dead letter store error: {0}")]
+    DeadLetterStoreError(String),
+    /// An error occurred when serializing or deserializing the state of the [Saga](crate::Saga)
+    /// with the given name.
+    #[error("Could not serialize or deserialize the state of saga {0}: {1}")]
+    SagaSerializationError(&'static str, #[source] serde_json::Error),
+    /// A [FaultyEventWriter](crate::testing::FaultyEventWriter) failed to write an event, as
+    /// instructed.
+    #[cfg(feature = "testing")]
+    #[error("Could not write event: {0}")]
+    EventWriterError(String),
+    /// A query handler failed to downcast a [BoxedQuery](crate::BoxedQuery), or the
+    /// [QueryBus](crate::QueryBus) failed to downcast the [result](crate::BoxedOutput) of a query.
+    #[error("Could not downcast query or query result to type {0}")]
+    QueryDowncastError(&'static str),
+    /// A [Query](crate::Query) was executed but the query bus does not have a corresponding
+    /// [QueryHandler](crate::QueryHandler).
+    #[error("Missing query handler for query {0}")]
+    MissingQueryHandler(&'static str),
+    /// An [EventPublisher](crate::EventPublisher) failed to publish an event.
+    #[error("Could not publish event: {0}")]
+    EventPublisherError(String),
+    /// An error occurred when serializing or deserializing the
+    /// [SerializableCommand](crate::SerializableCommand) with the given name.
+    #[error("Could not serialize or deserialize command {0}: {1}")]
+    CommandSerializationError(&'static str, #[source] serde_json::Error),
+    /// A [SerializedCommand](crate::SerializedCommand) was executed but its command is not
+    /// registered as a [serializable command](crate::Configuration::serializable_command).
+    #[error("Unknown serialized command {0}")]
+    UnknownCommand(String),
+    /// A [CommandHandler](crate::CommandHandler) returned an event that it does not
+    /// [declare](crate::CommandHandler::emits). Only reported when
+    /// [verifying declarations](crate::Configuration::verify_declarations).
+    #[error("Command handler {0} returned the undeclared event {1}")]
+    UndeclaredEvent(&'static str, &'static str),
+    /// An [EventHandler](crate::EventHandler) issued a command that it does not
+    /// [declare](crate::EventHandler::issues). Only reported when
+    /// [verifying declarations](crate::Configuration::verify_declarations).
+    #[error("Event handler {0} issued the undeclared command {1}")]
+    UndeclaredCommand(&'static str, &'static str),
+    /// The command with the given name was [scheduled](crate::Schedule::after) after a delay that
+    /// exceeds the range of the [Clock](crate::Clock) time.
+    #[error("Could not schedule command {0}: the delay is out of range")]
+    ScheduleOutOfRange(&'static str),
+    /// The event handler with the given name panicked, and the panic was caught by the
+    /// [CatchPanics](crate::CatchPanics) middleware.
+    #[error("Event handler {0} panicked: {1}")]
+    HandlerPanicked(&'static str, String),
+}
+
+/// An error returned by a [CommandBus](crate::CommandBus), with the context of the failure.
+///
+/// Wraps the error that stopped the execution, and records where it happened: the command being
+/// executed, the event being written or handled, the failing handler, and the cascade of commands
+/// and events that led to the failure.
+///
+/// # Example
+///
+/// ```
+/// # use presage::{command_handler, Command, CommandBus, Configuration, Error, Events};
+/// # #[derive(Command)]
+/// # struct CreateTodo;
+/// # #[command_handler]
+/// # async fn create_todo(_: &mut Context, _: CreateTodo) -> Result<Events, Error> {
+/// #     Err(Error::CommandDowncastError("unavailable"))
+/// # }
+/// # struct Context;
+/// # #[presage::async_trait]
+/// # impl presage::EventWriter for Context {
+/// #     type Error = Error;
+/// #     async fn write(&mut self, _: &presage::SerializedEvent) -> Result<(), Error> { Ok(()) }
+/// # }
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let command_bus = CommandBus::new().configure(Configuration::new().command_handler(&create_todo));
+///
+/// let error = command_bus.execute(&mut Context, CreateTodo).await.unwrap_err();
+///
+/// assert_eq!(error.command(), Some("create-todo"));
+/// assert_eq!(error.handler(), Some("create_todo"));
+/// assert_eq!(error.path(), ["create-todo"]);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ExecutionError<E> {
+    error: E,
+    command: Option<&'static str>,
+    event: Option<&'static str>,
+    handler: Option<&'static str>,
+    path: Vec<&'static str>,
+    compensations: Vec<Compensation<E>>,
+}
+
+impl<E> ExecutionError<E> {
+    pub(crate) fn new(error: E, command: Option<&'static str>, path: &[&'static str]) -> Self {
+        Self {
+            error,
+            command,
+            event: None,
+            handler: None,
+            path: path.to_vec(),
+            compensations: Vec::new(),
+        }
+    }
+
+    pub(crate) fn at_event(mut self, event: &'static str) -> Self {
+        self.event = Some(event);
+        self
+    }
+
+    pub(crate) fn in_handler(mut self, handler: &'static str) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    pub(crate) fn compensated(mut self, compensations: Vec<Compensation<E>>) -> Self {
+        self.compensations = compensations;
+        self
+    }
+
+    /// The error that stopped the execution.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Consumes the [ExecutionError] and returns the error that stopped the execution.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    /// The name of the command being executed, or of the command that issued the event being
+    /// written or handled. There is no command when the failure happened while
+    /// [re-driving](crate::CommandBus::redrive) a dead letter.
+    pub fn command(&self) -> Option<&'static str> {
+        self.command
+    }
+
+    /// The name of the event being written or handled, if the failure happened after the command
+    /// was handled.
+    pub fn event(&self) -> Option<&'static str> {
+        self.event
+    }
+
+    /// The name of the failing command or event handler, if the failure happened in a handler.
+    pub fn handler(&self) -> Option<&'static str> {
+        self.handler
+    }
+
+    /// The names of the commands and events that led to the failure, alternating from the executed
+    /// command to the command or event being processed.
+    pub fn path(&self) -> &[&'static str] {
+        &self.path
+    }
+
+    /// The outcomes of the compensating commands executed after the failure, in the order in which
+    /// they were executed (i.e., the reverse order of the compensated commands).
+    pub fn compensations(&self) -> &[Compensation<E>] {
+        &self.compensations
+    }
+}
+
+impl<E> Display for ExecutionError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.handler {
+            Some(handler) => write!(f, "Handler {handler} failed at ")?,
+            None => write!(f, "Execution failed at ")?,
+        }
+        write!(f, "{}: {}", self.path.join(" > "), self.error)
+    }
+}
+
+/// The outcome of a compensating command, executed by a [CommandBus](crate::CommandBus) to undo the
+/// effects of a command after a failure.
+///
+/// Compensating commands are registered with
+/// [Configuration::compensation](crate::Configuration::compensation).
+#[derive(Debug)]
+pub struct Compensation<E> {
+    command: &'static str,
+    error: Option<ExecutionError<E>>,
+}
+
+impl<E> Compensation<E> {
+    pub(crate) fn new(command: &'static str, error: Option<ExecutionError<E>>) -> Self {
+        Self { command, error }
+    }
+
+    /// The name of the compensating command.
+    pub fn command(&self) -> &'static str {
+        self.command
+    }
+
+    /// The error that stopped the execution of the compensating command, if any.
+    pub fn error(&self) -> Option<&ExecutionError<E>> {
+        self.error.as_ref()
+    }
+
+    /// Returns `true` if the compensating command was executed successfully.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl<E> std::error::Error for ExecutionError<E>
+where
+    E: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        async_trait, command_handler, commands, event_handler, events, Command, CommandBus,
+        Commands, Configuration, Event, EventWriter, Events, SerializedEvent,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[tokio::test]
+    async fn test_failing_command_in_cascade() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .command_handler(&notify_owner)
+                .event_handler(&on_item_created),
+        );
+
+        let error = command_bus
+            .execute(&mut TestContext { fail_event: false }, CreateItem)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.command(), Some("notify-owner"));
+        assert_eq!(error.event(), None);
+        assert_eq!(error.handler(), Some("notify_owner"));
+        assert_eq!(
+            error.path(),
+            ["create-item", "item-created", "notify-owner"]
+        );
+        assert_eq!(
+            error.to_string(),
+            "Handler notify_owner failed at create-item > item-created > notify-owner: \
+             Missing command handler for command mailer"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_event_handler() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .event_handler(&on_item_created),
+        );
+
+        let error = command_bus
+            .execute(&mut TestContext { fail_event: true }, CreateItem)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.command(), Some("create-item"));
+        assert_eq!(error.event(), Some("item-created"));
+        assert_eq!(error.handler(), Some("on_item_created"));
+        assert_eq!(error.path(), ["create-item", "item-created"]);
+        assert!(matches!(
+            error.into_inner(),
+            Error::SerializationError("item-created", _)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_command_is_not_attributed_to_its_handler() {
+        let command_bus =
+            CommandBus::new().configure(Configuration::new().command_handler(&rename_item));
+
+        let error = command_bus
+            .execute(
+                &mut TestContext { fail_event: false },
+                RenameItem(String::new()),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.handler(), None);
+        assert_eq!(
+            error.to_string(),
+            "Execution failed at rename-item: Invalid command rename-item: 0 must not be blank"
+        );
+    }
+
+    struct TestContext {
+        fail_event: bool,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CreateItem;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated;
+
+    #[derive(Event, Serialize, Deserialize)]
+    #[presage(name = "item-created")]
+    struct ItemCreatedWithName {
+        #[allow(dead_code)]
+        name: String,
+    }
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated))
+    }
+
+    #[event_handler(events = [ItemCreated])]
+    async fn on_item_created(
+        context: &mut TestContext,
+        event: &SerializedEvent,
+    ) -> Result<Commands, Error> {
+        if context.fail_event {
+            event.clone().deserialize::<ItemCreatedWithName>()?;
+        }
+        Ok(commands!(NotifyOwner))
+    }
+
+    #[derive(Command)]
+    struct NotifyOwner;
+
+    #[derive(Command)]
+    #[presage(validate)]
+    struct RenameItem(#[validate(not_blank)] String);
+
+    #[command_handler]
+    async fn rename_item(_: &mut TestContext, _: RenameItem) -> Result<Events, Error> {
+        Ok(Events::new())
+    }
+
+    #[command_handler]
+    async fn notify_owner(_: &mut TestContext, _: NotifyOwner) -> Result<Events, Error> {
+        Err(Error::MissingCommandHandler("mailer"))
+    }
 }
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::any::type_name;
 
 use crate::{Aggregate, Commands, Error, Id};
 
@@ -19,7 +20,7 @@ use crate::{Aggregate, Commands, Error, Id};
 ///
 /// ```
 /// #[derive(serde::Serialize, serde::Deserialize)]
-/// struct SystemStarted(std::time::Instant);
+/// struct SystemStarted(std::time::SystemTime);
 ///
 /// impl presage::Event for SystemStarted {
 ///     const NAME: &'static str = "system-started";
@@ -33,9 +34,20 @@ pub trait Event: Serialize + DeserializeOwned {
     fn serialize(self) -> Result<SerializedEvent, Error> {
         Ok(SerializedEvent {
             name: Self::NAME,
-            value: serde_json::to_value(self)?,
+            value: serde_json::to_value(self)
+                .map_err(|error| Error::SerializationError(Self::NAME, error))?,
+            metadata: Map::new(),
         })
     }
+
+    /// The JSON schema of the event, added to the
+    /// [schema of the configuration](crate::Configuration::schema). By default, an event has no
+    /// schema. When deriving [Event] or [AggregateEvent], the schema is derived with
+    /// `#[presage(schema)]`.
+    #[cfg(feature = "schema")]
+    fn schema(_generator: &mut schemars::SchemaGenerator) -> Option<schemars::Schema> {
+        None
+    }
 }
 
 /// An [Event] that creates, updates, or deletes an aggregate.
@@ -48,6 +60,18 @@ pub trait Event: Serialize + DeserializeOwned {
 ///
 /// ```
 /// use presage::{AggregateEvent, Event, Id};
+/// # use presage::Aggregate;
+/// #
+/// # pub struct Todo { id: Id<Todo> }
+/// # impl Aggregate for Todo {
+/// #     type Id = u64;
+/// #     type CreationEvent = TodoCreated;
+/// #     type UpdateEvent = TodoCreated;
+/// #     type DeletionEvent = TodoCreated;
+/// #     fn id(&self) -> Id<Self> { self.id }
+/// #     fn new(event: TodoCreated) -> Self { Self { id: event.id } }
+/// #     fn apply(&mut self, _: TodoCreated) {}
+/// # }
 ///
 /// #[derive(serde::Serialize, serde::Deserialize)]
 /// pub struct TodoCreated {
@@ -77,23 +101,49 @@ pub trait AggregateEvent: Event {
 
 /// An event that has been serialized to be issued by a command.
 ///
-/// Can be created from an [Event].
+/// Can be created from an [Event]. Besides the serialized event, it holds metadata that are not
+/// part of the event itself (e.g., a tenant or a correlation id), and that can be added by an
+/// [EventMiddleware](crate::EventMiddleware).
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SerializedEvent {
     name: &'static str,
     value: Value,
+    metadata: Map<String, Value>,
 }
 
 impl SerializedEvent {
+    pub(crate) fn new(name: &'static str, value: Value, metadata: Map<String, Value>) -> Self {
+        Self {
+            name,
+            value,
+            metadata,
+        }
+    }
+
     /// Tries to deserialize to a concrete [Event].
     pub fn deserialize<E: Event>(self) -> Result<E, Error> {
-        Ok(serde_json::from_value(self.value)?)
+        serde_json::from_value(self.value)
+            .map_err(|error| Error::SerializationError(E::NAME, error))
     }
 
     /// The name of the serialized event
     pub fn name(&self) -> &'static str {
         self.name
     }
+
+    pub(crate) fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// The metadata of the serialized event
+    pub fn metadata(&self) -> &Map<String, Value> {
+        &self.metadata
+    }
+
+    /// Mutable access to the metadata of the serialized event
+    pub fn metadata_mut(&mut self) -> &mut Map<String, Value> {
+        &mut self.metadata
+    }
 }
 
 /// Wrapper for a [Vec] of [serialized events](SerializedEvent).
@@ -147,9 +197,41 @@ macro_rules! events {
 /// * `E` - the type of errors returned if the handler fails
 #[async_trait]
 pub trait EventHandler<C, E>: Send + Sync {
+    /// The name of the handler. Defaults to the name of the implementing type.
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// A key identifying the handler, used to configure it (e.g., its
+    /// [retry policy](crate::Configuration::event_handler_with_retry)). Unlike the name, it must be
+    /// unique among the event handlers of a configuration. Defaults to the name of the implementing
+    /// type, which includes its module path.
+    fn key(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
     /// The names of the handled events.
     fn event_names(&self) -> &[&'static str];
 
+    /// The names of the commands that the handler may issue, as declared by the handler, e.g., with
+    /// `#[event_handler(issues = [ArchiveTodo])]`. Used to render the [flow](crate::EventFlow) of a
+    /// configuration, and to [verify](crate::Configuration::verify_declarations) the handler. An
+    /// empty declaration states that the handler issues no command. By default, nothing is
+    /// declared.
+    fn issues(&self) -> Option<&[&'static str]> {
+        None
+    }
+
+    /// The JSON schemas of the handled events that have one (see [Event::schema]), with their
+    /// names. Implemented by the `event_handler` macro. By default, no schema is returned.
+    #[cfg(feature = "schema")]
+    fn event_schemas(
+        &self,
+        _generator: &mut schemars::SchemaGenerator,
+    ) -> Vec<(&'static str, schemars::Schema)> {
+        Vec::new()
+    }
+
     /// Handles an event with the given context.
     async fn handle(&self, context: &mut C, event: &SerializedEvent) -> Result<Commands, E>;
 }
@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::Configuration;
+
+/// The flow of commands and events of a [Configuration], from commands to command handlers, to
+/// events, to event handlers, and back to commands.
+///
+/// Created with [Configuration::event_flow()]. The events returned by command handlers and the
+/// commands issued by event handlers are only known when the handlers declare them (see
+/// [CommandHandler::emits](crate::CommandHandler::emits) and
+/// [EventHandler::issues](crate::EventHandler::issues)).
+///
+/// The flow can be rendered as a diagram, with [to_dot()](Self::to_dot) for
+/// [Graphviz](https://graphviz.org) or [to_mermaid()](Self::to_mermaid) for
+/// [Mermaid](https://mermaid.js.org).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFlow {
+    command_handlers: Vec<HandlerFlow>,
+    event_handlers: Vec<HandlerFlow>,
+}
+
+/// A handler in an [EventFlow], with the commands or events it handles, and the events or commands
+/// it declares to produce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandlerFlow {
+    key: &'static str,
+    name: &'static str,
+    handles: Vec<&'static str>,
+    produces: Vec<&'static str>,
+}
+
+impl HandlerFlow {
+    /// The name of the handler.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The names of the handled command or events.
+    pub fn handles(&self) -> &[&'static str] {
+        &self.handles
+    }
+
+    /// The names of the events returned by a command handler, or of the commands issued by an
+    /// event handler, as declared by the handler.
+    pub fn produces(&self) -> &[&'static str] {
+        &self.produces
+    }
+}
+
+type Node = (NodeKind, &'static str);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeKind {
+    Command,
+    CommandHandler,
+    Event,
+    EventHandler,
+}
+
+impl EventFlow {
+    pub(crate) fn new<C, E>(configuration: &Configuration<C, E>) -> Self {
+        let mut command_handlers: Vec<_> = configuration
+            .command_handlers
+            .values()
+            .map(|handler| HandlerFlow {
+                key: handler.command_name(),
+                name: handler.name(),
+                handles: vec![handler.command_name()],
+                produces: handler.emits().unwrap_or_default().to_vec(),
+            })
+            .collect();
+        command_handlers.sort_by_key(|handler| (handler.name, handler.handles[0]));
+
+        let mut event_handlers: BTreeMap<_, _> = BTreeMap::new();
+        for handler in configuration.event_handlers.values().flatten() {
+            event_handlers
+                .entry((handler.name(), handler.key()))
+                .or_insert_with(|| HandlerFlow {
+                    key: handler.key(),
+                    name: handler.name(),
+                    handles: handler.event_names().to_vec(),
+                    produces: handler.issues().unwrap_or_default().to_vec(),
+                });
+        }
+
+        Self {
+            command_handlers,
+            event_handlers: event_handlers.into_values().collect(),
+        }
+    }
+
+    /// The command handlers of the configuration, sorted by name.
+    pub fn command_handlers(&self) -> &[HandlerFlow] {
+        &self.command_handlers
+    }
+
+    /// The event handlers of the configuration, sorted by name.
+    pub fn event_handlers(&self) -> &[HandlerFlow] {
+        &self.event_handlers
+    }
+
+    /// Renders the flow as a [Graphviz](https://graphviz.org) digraph, in the DOT language.
+    pub fn to_dot(&self) -> String {
+        let (nodes, edges) = self.graph();
+        let mut dot = String::from("digraph presage {\n    rankdir=LR;\n");
+        for (id, (kind, name)) in nodes.iter().enumerate() {
+            let shape = match kind {
+                NodeKind::Command => "box",
+                NodeKind::Event => "ellipse",
+                NodeKind::CommandHandler | NodeKind::EventHandler => "box, style=rounded",
+            };
+            let label = name.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(dot, "    n{id} [label=\"{label}\", shape={shape}];");
+        }
+        for (from, to) in edges {
+            let _ = writeln!(dot, "    n{from} -> n{to};");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the flow as a [Mermaid](https://mermaid.js.org) flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let (nodes, edges) = self.graph();
+        let mut mermaid = String::from("flowchart LR\n");
+        for (id, (kind, name)) in nodes.iter().enumerate() {
+            let label = name.replace('"', "#quot;");
+            let _ = match kind {
+                NodeKind::Command => writeln!(mermaid, "    n{id}[\"{label}\"]"),
+                NodeKind::Event => writeln!(mermaid, "    n{id}[/\"{label}\"/]"),
+                NodeKind::CommandHandler | NodeKind::EventHandler => {
+                    writeln!(mermaid, "    n{id}(\"{label}\")")
+                }
+            };
+        }
+        for (from, to) in edges {
+            let _ = writeln!(mermaid, "    n{from} --> n{to}");
+        }
+        mermaid
+    }
+
+    /// Lists the nodes of the flow, and the edges between them as pairs of indices of nodes.
+    fn graph(&self) -> (Vec<Node>, Vec<(usize, usize)>) {
+        let mut ids = BTreeMap::new();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        // Handlers are identified by their key, since several handlers may have the same name
+        let mut node = |kind, key, name| {
+            *ids.entry((kind, key)).or_insert_with(|| {
+                nodes.push((kind, name));
+                nodes.len() - 1
+            })
+        };
+
+        let handlers = [
+            (
+                &self.command_handlers,
+                NodeKind::Command,
+                NodeKind::CommandHandler,
+                NodeKind::Event,
+            ),
+            (
+                &self.event_handlers,
+                NodeKind::Event,
+                NodeKind::EventHandler,
+                NodeKind::Command,
+            ),
+        ];
+        for (handlers, input, kind, output) in handlers {
+            for handler in handlers {
+                let handler_node = node(kind, handler.key, handler.name);
+                for name in &handler.handles {
+                    edges.push((node(input, name, name), handler_node));
+                }
+                for name in &handler.produces {
+                    edges.push((handler_node, node(output, name, name)));
+                }
+            }
+        }
+
+        (nodes, edges)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        commands, BoxedCommand, CommandHandler, Commands, Error, EventHandler, Events,
+        SerializedEvent,
+    };
+    use async_trait::async_trait;
+
+    #[test]
+    fn test_event_flow() {
+        let flow = configuration().event_flow();
+
+        assert_eq!(flow.command_handlers().len(), 1);
+        assert_eq!(flow.command_handlers()[0].handles(), ["place-order"]);
+        assert_eq!(flow.command_handlers()[0].produces(), ["order-placed"]);
+        assert_eq!(flow.event_handlers().len(), 1);
+        assert_eq!(flow.event_handlers()[0].name(), "reserve_stock");
+        assert_eq!(flow.event_handlers()[0].produces(), ["reserve-stock"]);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = configuration().event_flow().to_dot();
+
+        assert_eq!(
+            dot,
+            r#"digraph presage {
+    rankdir=LR;
+    n0 [label="place_order", shape=box, style=rounded];
+    n1 [label="place-order", shape=box];
+    n2 [label="order-placed", shape=ellipse];
+    n3 [label="reserve_stock", shape=box, style=rounded];
+    n4 [label="reserve-stock", shape=box];
+    n1 -> n0;
+    n0 -> n2;
+    n2 -> n3;
+    n3 -> n4;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let mermaid = configuration().event_flow().to_mermaid();
+
+        assert_eq!(
+            mermaid,
+            r#"flowchart LR
+    n0("place_order")
+    n1["place-order"]
+    n2[/"order-placed"/]
+    n3("reserve_stock")
+    n4["reserve-stock"]
+    n1 --> n0
+    n0 --> n2
+    n2 --> n3
+    n3 --> n4
+"#
+        );
+    }
+
+    #[test]
+    fn test_handlers_with_the_same_name_are_distinct() {
+        let flow = configuration()
+            .event_handler(&other::ReserveStockHandler)
+            .event_flow();
+        let dot = flow.to_dot();
+
+        assert_eq!(flow.event_handlers().len(), 2);
+        assert_eq!(dot.matches(r#"[label="reserve_stock""#).count(), 2);
+    }
+
+    fn configuration() -> Configuration<Shop, Error> {
+        Configuration::new()
+            .command_handler(&PlaceOrderHandler)
+            .event_handler(&ReserveStockHandler)
+    }
+
+    struct Shop;
+
+    struct PlaceOrderHandler;
+
+    #[async_trait]
+    impl CommandHandler<Shop, Error> for PlaceOrderHandler {
+        fn name(&self) -> &'static str {
+            "place_order"
+        }
+
+        fn command_name(&self) -> &'static str {
+            "place-order"
+        }
+
+        fn emits(&self) -> Option<&[&'static str]> {
+            Some(&["order-placed"])
+        }
+
+        async fn handle(&self, _: &mut Shop, _: BoxedCommand) -> Result<Events, Error> {
+            Ok(Events::new())
+        }
+    }
+
+    struct ReserveStockHandler;
+
+    #[async_trait]
+    impl EventHandler<Shop, Error> for ReserveStockHandler {
+        fn name(&self) -> &'static str {
+            "reserve_stock"
+        }
+
+        fn event_names(&self) -> &[&'static str] {
+            &["order-placed"]
+        }
+
+        fn issues(&self) -> Option<&[&'static str]> {
+            Some(&["reserve-stock"])
+        }
+
+        async fn handle(&self, _: &mut Shop, _: &SerializedEvent) -> Result<Commands, Error> {
+            Ok(commands!())
+        }
+    }
+
+    mod other {
+        use super::*;
+
+        pub struct ReserveStockHandler;
+
+        #[async_trait]
+        impl EventHandler<Shop, Error> for ReserveStockHandler {
+            fn name(&self) -> &'static str {
+                "reserve_stock"
+            }
+
+            fn event_names(&self) -> &[&'static str] {
+                &["order-placed"]
+            }
+
+            async fn handle(&self, _: &mut Shop, _: &SerializedEvent) -> Result<Commands, Error> {
+                Ok(commands!())
+            }
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use axum::extract::rejection::JsonRejection;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::{CommandBus, Error, EventWriter, ExecutionError, Principal, SerializedCommand};
+
+/// An error that can be converted into the status code of an HTTP response.
+///
+/// Implemented for [Error]. The error type of the handlers must implement it to be used with a
+/// [command_router], usually by delegating the errors of présage to the implementation for [Error].
+///
+/// A [forbidden](StatusCode::FORBIDDEN) status code is reported as
+/// [unauthorized](StatusCode::UNAUTHORIZED) when the request was executed without any principal.
+pub trait ErrorStatus {
+    /// The status code of the HTTP response reporting the error.
+    fn status_code(&self) -> StatusCode;
+}
+
+impl ErrorStatus for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::InvalidCommand(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::CommandSerializationError(..) => StatusCode::BAD_REQUEST,
+            Error::UnknownCommand(_) => StatusCode::NOT_FOUND,
+            Error::Unauthorized(..) => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The report of the execution of a command, returned in the body of the responses of a
+/// [command_router].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    /// The name of the executed command.
+    pub command: String,
+    /// Whether the execution succeeded.
+    pub success: bool,
+    /// The error that stopped the execution, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The cascade of commands and events that led to the failure.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    /// The failing handler, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handler: Option<String>,
+    /// The names of the compensating commands that were executed after the failure, with their
+    /// success.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compensations: Vec<(String, bool)>,
+}
+
+impl ExecutionReport {
+    fn success(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            success: true,
+            error: None,
+            path: Vec::new(),
+            handler: None,
+            compensations: Vec::new(),
+        }
+    }
+
+    fn rejected(command: &str, rejection: &JsonRejection) -> Self {
+        Self {
+            command: command.to_string(),
+            success: false,
+            error: Some(rejection.body_text()),
+            path: Vec::new(),
+            handler: None,
+            compensations: Vec::new(),
+        }
+    }
+
+    fn failure<E: Display>(command: &str, error: &ExecutionError<E>) -> Self {
+        Self {
+            command: command.to_string(),
+            success: false,
+            error: Some(error.error().to_string()),
+            path: error.path().iter().map(ToString::to_string).collect(),
+            handler: error.handler().map(ToString::to_string),
+            compensations: error
+                .compensations()
+                .iter()
+                .map(|compensation| {
+                    (
+                        compensation.command().to_string(),
+                        compensation.is_success(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+type ExtractPrincipal = dyn Fn(&HeaderMap) -> Option<Principal> + Send + Sync;
+
+struct Gateway<C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    command_bus: CommandBus<C, E>,
+    context: Box<dyn Fn() -> C + Send + Sync>,
+    principal: Box<ExtractPrincipal>,
+}
+
+/// Creates an [axum] router exposing the [serializable commands](crate::SerializableCommand) of a
+/// command bus.
+///
+/// Each command registered with
+/// [Configuration::serializable_command()](crate::Configuration::serializable_command) is exposed
+/// as `POST /commands/{name}`, with the payload of the command as JSON body. Each request executes
+/// the command within a new context, created with the given function, and responds with an
+/// [ExecutionReport]. When the execution fails, the status code of the response is given by the
+/// [ErrorStatus] implementation of the error. A body that is not valid JSON is rejected with a
+/// [bad request](StatusCode::BAD_REQUEST) status code.
+///
+/// The commands are executed without any [principal](Principal), so that the commands whose
+/// handlers [require roles](crate::CommandHandler::requires) are always rejected as
+/// [unauthorized](StatusCode::UNAUTHORIZED): use [command_router_with_principal] to
+/// authenticate the requests. Only commands are
+/// exposed, [queries](crate::Query) are not.
+///
+/// # Example
+///
+/// ```
+/// # use presage::{command_handler, Command, CommandBus, Configuration, Error, Events};
+/// # use presage::SerializableCommand;
+/// # #[derive(Command, SerializableCommand, serde::Serialize, serde::Deserialize)]
+/// # struct CreateTodo { name: String }
+/// # #[command_handler]
+/// # async fn create_todo(_: &mut Context, _: CreateTodo) -> Result<Events, Error> {
+/// #     Ok(Events::new())
+/// # }
+/// # struct Context;
+/// # #[presage::async_trait]
+/// # impl presage::EventWriter for Context {
+/// #     type Error = Error;
+/// #     async fn write(&mut self, _: &presage::SerializedEvent) -> Result<(), Error> { Ok(()) }
+/// # }
+/// let command_bus = CommandBus::new().configure(
+///     Configuration::new()
+///         .command_handler(&create_todo)
+///         .serializable_command::<CreateTodo>(),
+/// );
+///
+/// let router: axum::Router = presage::command_router(command_bus, || Context);
+/// ```
+pub fn command_router<C, E>(
+    command_bus: CommandBus<C, E>,
+    context: impl Fn() -> C + Send + Sync + 'static,
+) -> Router
+where
+    C: EventWriter<Error = E> + Send + 'static,
+    E: From<Error> + ErrorStatus + Display + Send + Sync + 'static,
+{
+    command_router_with_principal(command_bus, context, |_| None)
+}
+
+/// Creates an [axum] router exposing the [serializable commands](crate::SerializableCommand) of a
+/// command bus, like [command_router], executing each command on behalf of the [Principal]
+/// extracted from the headers of the request.
+///
+/// When the function returns no principal, the command is executed without any principal, and is
+/// rejected as [unauthorized](StatusCode::UNAUTHORIZED) if its handler requires roles. The function
+/// is only responsible for authenticating the request, the roles of the principal are checked by
+/// the command bus, rejecting the command as [forbidden](StatusCode::FORBIDDEN) when missing.
+///
+/// # Example
+///
+/// ```
+/// # use presage::{command_handler, Command, CommandBus, Configuration, Error, Events, Principal};
+/// # use presage::SerializableCommand;
+/// # #[derive(Command, SerializableCommand, serde::Serialize, serde::Deserialize)]
+/// # struct CreateTodo { name: String }
+/// # #[command_handler(requires = "todo:write")]
+/// # async fn create_todo(_: &mut Context, _: CreateTodo) -> Result<Events, Error> {
+/// #     Ok(Events::new())
+/// # }
+/// # struct Context;
+/// # #[presage::async_trait]
+/// # impl presage::EventWriter for Context {
+/// #     type Error = Error;
+/// #     async fn write(&mut self, _: &presage::SerializedEvent) -> Result<(), Error> { Ok(()) }
+/// # }
+/// # fn verify(_: &str) -> Option<Principal> { None }
+/// let command_bus = CommandBus::new().configure(
+///     Configuration::new()
+///         .command_handler(&create_todo)
+///         .serializable_command::<CreateTodo>(),
+/// );
+///
+/// let router: axum::Router =
+///     presage::command_router_with_principal(command_bus, || Context, |headers| {
+///         let token = headers.get("authorization")?.to_str().ok()?;
+///         verify(token)
+///     });
+/// ```
+pub fn command_router_with_principal<C, E>(
+    command_bus: CommandBus<C, E>,
+    context: impl Fn() -> C + Send + Sync + 'static,
+    principal: impl Fn(&HeaderMap) -> Option<Principal> + Send + Sync + 'static,
+) -> Router
+where
+    C: EventWriter<Error = E> + Send + 'static,
+    E: From<Error> + ErrorStatus + Display + Send + Sync + 'static,
+{
+    let names: Vec<_> = command_bus.serializable_command_names().collect();
+    let gateway = Arc::new(Gateway {
+        command_bus,
+        context: Box::new(context),
+        principal: Box::new(principal),
+    });
+    names.into_iter().fold(Router::new(), |router, name| {
+        router.route(
+            &format!("/commands/{name}"),
+            post(
+                move |State(gateway): State<Arc<Gateway<C, E>>>,
+                      headers: HeaderMap,
+                      payload: Result<Json<Value>, JsonRejection>| {
+                    execute(gateway, headers, name, payload)
+                },
+            )
+            .with_state(gateway.clone()),
+        )
+    })
+}
+
+async fn execute<C, E>(
+    gateway: Arc<Gateway<C, E>>,
+    headers: HeaderMap,
+    name: &'static str,
+    payload: Result<Json<Value>, JsonRejection>,
+) -> Response
+where
+    C: EventWriter<Error = E> + Send + 'static,
+    E: From<Error> + ErrorStatus + Display + Send + Sync + 'static,
+{
+    let command = match payload {
+        Ok(Json(payload)) => SerializedCommand::new(name, payload),
+        Err(rejection) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ExecutionReport::rejected(name, &rejection)),
+            )
+                .into_response()
+        }
+    };
+    let mut context = (gateway.context)();
+    let principal = (gateway.principal)(&headers);
+    let result = match &principal {
+        Some(principal) => {
+            gateway
+                .command_bus
+                .execute_serialized_as(&mut context, principal, command)
+                .await
+        }
+        None => {
+            gateway
+                .command_bus
+                .execute_serialized(&mut context, command)
+                .await
+        }
+    };
+    match result {
+        Ok(()) => Json(ExecutionReport::success(name)).into_response(),
+        Err(error) => {
+            let status_code = match error.error().status_code() {
+                StatusCode::FORBIDDEN if principal.is_none() => StatusCode::UNAUTHORIZED,
+                status_code => status_code,
+            };
+            (status_code, Json(ExecutionReport::failure(name, &error))).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, Command, Configuration, Events, SerializableCommand, SerializedEvent,
+    };
+    use async_trait::async_trait;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_commands_are_executed() {
+        let (status, report) = post_command("/commands/rename-item", r#"{"name": "Box"}"#).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report, ExecutionReport::success("rename-item"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_commands_are_unprocessable() {
+        let (status, report) = post_command("/commands/rename-item", r#"{"name": ""}"#).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(!report.success);
+        assert_eq!(report.path, ["rename-item"]);
+        assert_eq!(report.handler, None);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_commands_are_bad_requests() {
+        let (status, report) = post_command("/commands/rename-item", r#"{"label": "Box"}"#).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(!report.success);
+    }
+
+    #[tokio::test]
+    async fn test_non_json_bodies_are_bad_requests() {
+        let request = Request::post("/commands/rename-item")
+            .header("content-type", "text/plain")
+            .body(Body::from("Box"))
+            .unwrap();
+
+        let (status, report) = send(request).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(report.command, "rename-item");
+        assert!(!report.success);
+        assert!(report.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_commands_requiring_roles_are_unauthorized_without_principal() {
+        let (status, report) = post_command("/commands/remove-item", "{}").await;
+
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(!report.success);
+    }
+
+    #[tokio::test]
+    async fn test_commands_requiring_missing_roles_are_forbidden() {
+        let request = Request::post("/commands/remove-item")
+            .header("content-type", "application/json")
+            .header("x-user", "bob")
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let (status, report) = send(request).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(!report.success);
+    }
+
+    #[tokio::test]
+    async fn test_commands_are_executed_on_behalf_of_the_principal() {
+        let request = Request::post("/commands/remove-item")
+            .header("content-type", "application/json")
+            .header("x-user", "alice")
+            .header("x-role", "item:remove")
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let (status, report) = send(request).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report, ExecutionReport::success("remove-item"));
+    }
+
+    async fn post_command(uri: &str, body: &'static str) -> (StatusCode, ExecutionReport) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        send(request).await
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, ExecutionReport) {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&rename_item)
+                .command_handler(&remove_item)
+                .serializable_command::<RenameItem>()
+                .serializable_command::<RemoveItem>(),
+        );
+
+        let response = command_router_with_principal(
+            command_bus,
+            || Inventory,
+            |headers| {
+                let user = headers.get("x-user")?.to_str().ok()?;
+                let principal = Principal::user(user);
+                Some(
+                    match headers.get("x-role").and_then(|role| role.to_str().ok()) {
+                        Some(role) => principal.with_role(role),
+                        None => principal,
+                    },
+                )
+            },
+        )
+        .oneshot(request)
+        .await
+        .unwrap();
+
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    struct Inventory;
+
+    #[async_trait]
+    impl EventWriter for Inventory {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command, SerializableCommand, Serialize, Deserialize)]
+    #[presage(validate)]
+    struct RenameItem {
+        #[validate(not_blank)]
+        name: String,
+    }
+
+    #[command_handler]
+    async fn rename_item(_: &mut Inventory, _: RenameItem) -> Result<Events, Error> {
+        Ok(Events::new())
+    }
+
+    #[derive(Command, SerializableCommand, Serialize, Deserialize)]
+    struct RemoveItem {}
+
+    #[command_handler(requires = "item:remove")]
+    async fn remove_item(_: &mut Inventory, _: RemoveItem) -> Result<Events, Error> {
+        Ok(Events::new())
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generates new ids, e.g., for [aggregates](crate::Aggregate).
+///
+/// Like the [Clock](crate::Clock), an id generator is meant to be accessed by handlers through
+/// their context, so that the ids are reproducible in tests. It is implemented by any function
+/// returning an id (e.g., `uuid::Uuid::new_v4`), and by [SequentialIdGenerator].
+///
+/// # Example
+///
+/// ```
+/// use presage::{IdGenerator, SequentialIdGenerator};
+///
+/// struct Context {
+///     ids: Box<dyn IdGenerator<u64>>,
+/// }
+///
+/// impl IdGenerator<u64> for Context {
+///     fn next_id(&self) -> u64 {
+///         self.ids.next_id()
+///     }
+/// }
+///
+/// let context = Context { ids: Box::new(SequentialIdGenerator::new(1)) };
+///
+/// assert_eq!(context.next_id(), 1);
+/// assert_eq!(context.next_id(), 2);
+/// ```
+pub trait IdGenerator<I>: Send + Sync {
+    /// Generates a new id.
+    fn next_id(&self) -> I;
+}
+
+impl<I, F> IdGenerator<I> for F
+where
+    F: Fn() -> I + Send + Sync,
+{
+    fn next_id(&self) -> I {
+        self()
+    }
+}
+
+/// An [IdGenerator] that returns consecutive integers, starting at a given value. It can generate
+/// any type that can be created from a [u64], and other types through [map()](Self::map).
+#[derive(Debug, Default)]
+pub struct SequentialIdGenerator {
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    /// Creates a new generator, whose first id is the given value.
+    pub const fn new(first: u64) -> Self {
+        Self {
+            next: AtomicU64::new(first),
+        }
+    }
+
+    /// Converts the consecutive integers into ids of another type, e.g., with
+    /// `|n| Uuid::from_u64_pair(0, n)` for types that cannot be created from a [u64].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use presage::{IdGenerator, SequentialIdGenerator};
+    ///
+    /// let ids = SequentialIdGenerator::new(1).map(|n| format!("todo-{n}"));
+    ///
+    /// assert_eq!(ids.next_id(), "todo-1");
+    /// assert_eq!(ids.next_id(), "todo-2");
+    /// ```
+    pub fn map<I>(self, map: impl Fn(u64) -> I + Send + Sync) -> impl IdGenerator<I> {
+        move || map(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl<I> IdGenerator<I> for SequentialIdGenerator
+where
+    I: From<u64>,
+{
+    fn next_id(&self) -> I {
+        self.next.fetch_add(1, Ordering::Relaxed).into()
+    }
+}
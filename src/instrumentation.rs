@@ -0,0 +1,414 @@
+use std::future::Future;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument};
+
+use crate::ExecutionError;
+
+/// A [tracing](https://docs.rs/tracing) span of the [CommandBus](crate::CommandBus). Does nothing
+/// unless the `tracing` feature is enabled.
+///
+/// The span of an execution is the parent of the spans of the commands it executes, including the
+/// commands issued by event handlers, whose span records the cascade that led to them. The span of
+/// a command is the parent of the spans of the event handlers that handle its events.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    inner: tracing::Span,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+impl Span {
+    /// The span of an execution of the command bus, started by the given command.
+    pub(crate) fn execution(command: Option<&'static str>) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: tracing::info_span!("presage.execute", command, failed = Empty),
+        }
+    }
+
+    /// The span of a command, issued by the given cascade of commands and events.
+    pub(crate) fn command(command: &'static str, path: &[&'static str]) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: tracing::info_span!(
+                "presage.command",
+                command,
+                handler = Empty,
+                cascade = %path.join(" > "),
+                events = Empty,
+                failed = Empty,
+            ),
+        }
+    }
+
+    /// The span of an event handler.
+    pub(crate) fn event_handler(event: &'static str, handler: &'static str) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: tracing::info_span!("presage.event_handler", event, handler, failed = Empty),
+        }
+    }
+
+    /// The current span.
+    pub(crate) fn current() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: tracing::Span::current(),
+        }
+    }
+
+    /// Records the name of the handler of a command.
+    pub(crate) fn record_handler(&self, handler: &'static str) {
+        #[cfg(feature = "tracing")]
+        self.inner.record("handler", handler);
+    }
+
+    /// Records the number of events returned by a command handler.
+    pub(crate) fn record_events(&self, count: usize) {
+        #[cfg(feature = "tracing")]
+        self.inner.record("events", count);
+    }
+
+    /// Records that the command or the event handler failed.
+    pub(crate) fn record_failure(&self) {
+        #[cfg(feature = "tracing")]
+        self.inner.record("failed", true);
+    }
+
+    /// Records that the execution failed, with the context of the failure.
+    pub(crate) fn record_error<E>(&self, error: &ExecutionError<E>) {
+        self.record_failure();
+        #[cfg(feature = "tracing")]
+        tracing::error!(
+            parent: &self.inner,
+            command = error.command(),
+            event = error.event(),
+            handler = error.handler(),
+            path = %error.path().join(" > "),
+            compensations = error.compensations().len(),
+            "Execution failed",
+        );
+    }
+
+    /// Runs the future within the span.
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        let future = future.instrument(self.inner.clone());
+        future.await
+    }
+}
+
+/// Measures the duration of a handler with a [metrics](https://docs.rs/metrics) histogram. Does
+/// nothing unless the `metrics` feature is enabled.
+pub(crate) struct Timer {
+    #[cfg(feature = "metrics")]
+    start: Instant,
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+impl Timer {
+    /// Starts measuring.
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            start: Instant::now(),
+        }
+    }
+
+    /// Records the duration of a command handler, including its retries.
+    pub(crate) fn record_command_handler(self, handler: &'static str) {
+        #[cfg(feature = "metrics")]
+        metrics::histogram!("presage_command_handler_duration_seconds", "handler" => handler)
+            .record(self.start.elapsed());
+    }
+
+    /// Records the duration of an event handler, including its retries.
+    pub(crate) fn record_event_handler(self, event: &'static str, handler: &'static str) {
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(
+            "presage_event_handler_duration_seconds",
+            "event" => event,
+            "handler" => handler,
+        )
+        .record(self.start.elapsed());
+    }
+}
+
+/// Counts an executed or failed command. Does nothing unless the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_command(command: &'static str, success: bool) {
+    #[cfg(feature = "metrics")]
+    if success {
+        metrics::counter!("presage_commands_executed_total", "command" => command).increment(1);
+    } else {
+        metrics::counter!("presage_commands_failed_total", "command" => command).increment(1);
+    }
+}
+
+/// Counts a written event. Does nothing unless the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_event_written(event: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("presage_events_written_total", "event" => event).increment(1);
+}
+
+/// Records the number of commands of a cascade, i.e., the commands executed one after the other
+/// from an initial command. Does nothing unless the `metrics` feature is enabled.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_cascade(commands: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!("presage_cascade_size").record(commands as f64);
+}
+
+#[cfg(all(test, any(feature = "tracing", feature = "metrics")))]
+mod test {
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        command_handler, commands, event_handler, events, Command, CommandBus, Commands,
+        Configuration, Error, Event, EventWriter, Events, SerializedEvent,
+    };
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics_are_recorded() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(async {
+                command_bus()
+                    .execute(&mut TestContext, CreateItem)
+                    .await
+                    .unwrap_err()
+            })
+        });
+
+        let mut metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<_> = key
+                    .key()
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect();
+                let value = match value {
+                    DebugValue::Counter(count) => count as usize,
+                    DebugValue::Histogram(values) => values.len(),
+                    DebugValue::Gauge(_) => 0,
+                };
+                (key.key().name().to_string(), labels.join(","), value)
+            })
+            .collect();
+        metrics.sort();
+
+        let metric =
+            |name: &str, labels: &str, value| (name.to_string(), labels.to_string(), value);
+        assert_eq!(
+            metrics,
+            vec![
+                metric("presage_cascade_size", "", 1),
+                metric(
+                    "presage_command_handler_duration_seconds",
+                    "handler=create_item",
+                    1
+                ),
+                metric(
+                    "presage_command_handler_duration_seconds",
+                    "handler=notify_owner",
+                    1
+                ),
+                metric("presage_commands_executed_total", "command=create-item", 1),
+                metric("presage_commands_failed_total", "command=notify-owner", 1),
+                metric(
+                    "presage_event_handler_duration_seconds",
+                    "event=item-created,handler=on_item_created",
+                    1
+                ),
+                metric("presage_events_written_total", "event=item-created", 1),
+            ]
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_spans_follow_the_cascade() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorder = tracing_recorder::Recorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        command_bus()
+            .execute(&mut TestContext, CreateItem)
+            .await
+            .unwrap_err();
+
+        let spans = recorder.spans.lock().unwrap();
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|span| (span.name, span.parent, span.fields.clone()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (
+                    "presage.execute",
+                    None,
+                    tracing_recorder::fields(&[("command", "create-item"), ("failed", "true")])
+                ),
+                (
+                    "presage.command",
+                    Some("presage.execute"),
+                    tracing_recorder::fields(&[
+                        ("cascade", "create-item"),
+                        ("command", "create-item"),
+                        ("events", "1"),
+                        ("handler", "create_item"),
+                    ])
+                ),
+                (
+                    "presage.event_handler",
+                    Some("presage.command"),
+                    tracing_recorder::fields(&[
+                        ("event", "item-created"),
+                        ("handler", "on_item_created")
+                    ])
+                ),
+                (
+                    "presage.command",
+                    Some("presage.execute"),
+                    tracing_recorder::fields(&[
+                        ("cascade", "create-item > item-created > notify-owner"),
+                        ("command", "notify-owner"),
+                        ("failed", "true"),
+                        ("handler", "notify_owner"),
+                    ])
+                ),
+            ]
+        );
+    }
+
+    fn command_bus() -> CommandBus<TestContext, Error> {
+        CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .command_handler(&notify_owner)
+                .event_handler(&on_item_created),
+        )
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_recorder {
+        use std::collections::BTreeMap;
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::Subscriber;
+        use tracing_subscriber::layer::Context;
+        use tracing_subscriber::registry::LookupSpan;
+        use tracing_subscriber::Layer;
+
+        pub(super) fn fields(fields: &[(&str, &str)]) -> BTreeMap<String, String> {
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        }
+
+        #[derive(Clone, Default)]
+        pub(super) struct Recorder {
+            pub(super) spans: Arc<Mutex<Vec<RecordedSpan>>>,
+        }
+
+        pub(super) struct RecordedSpan {
+            pub(super) name: &'static str,
+            pub(super) parent: Option<&'static str>,
+            pub(super) fields: BTreeMap<String, String>,
+        }
+
+        struct SpanIndex(usize);
+
+        impl Visit for RecordedSpan {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.fields.insert(field.name().into(), value.into());
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.fields
+                    .insert(field.name().into(), format!("{value:?}"));
+            }
+        }
+
+        impl<S> Layer<S> for Recorder
+        where
+            S: Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+                let span = context.span(id).unwrap();
+                let mut recorded = RecordedSpan {
+                    name: span.name(),
+                    parent: span.parent().map(|parent| parent.name()),
+                    fields: BTreeMap::new(),
+                };
+                attributes.record(&mut recorded);
+                let mut spans = self.spans.lock().unwrap();
+                span.extensions_mut().insert(SpanIndex(spans.len()));
+                spans.push(recorded);
+            }
+
+            fn on_record(&self, id: &Id, values: &Record<'_>, context: Context<'_, S>) {
+                let span = context.span(id).unwrap();
+                let extensions = span.extensions();
+                let index = extensions.get::<SpanIndex>().unwrap();
+                values.record(&mut self.spans.lock().unwrap()[index.0]);
+            }
+        }
+    }
+
+    struct TestContext;
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CreateItem;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated;
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated))
+    }
+
+    #[event_handler]
+    async fn on_item_created(_: &mut TestContext, _: ItemCreated) -> Result<Commands, Error> {
+        Ok(commands!(NotifyOwner))
+    }
+
+    #[derive(Command)]
+    struct NotifyOwner;
+
+    #[command_handler]
+    async fn notify_owner(_: &mut TestContext, _: NotifyOwner) -> Result<Events, Error> {
+        Err(Error::MissingCommandHandler("mailer"))
+    }
+}
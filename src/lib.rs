@@ -19,39 +19,186 @@
 //! handler is executed. Those event handlers can return new commands which are also executed. The
 //! process continues as long as events and commands are issued.
 //!
+//! The first failure stops the execution and is returned as an [ExecutionError], which records the
+//! failing command, event and handler, as well as the cascade of commands and events that led to it.
+//!
+//! Commands that implement [SerializableCommand] can also be sent from outside the process, as a
+//! [SerializedCommand] decoded by the command bus.
+//!
+//! ## Query bus
+//!
+//! The [QueryBus] executes [queries](Query), which read the system without modifying it. Each query
+//! declares the type of its result, and is handled by a [query handler](QueryHandler) within a
+//! shared context.
+//!
 //! ## Context
 //!
 //! [Command handlers](CommandHandler) and [event handlers](EventHandler) are executed within a
 //! mutable context. This context is specific to your application and contains whatever is necessary
 //! for the execution of the handlers. For instance, it can contain a connection to a database.
 //!
+//! To keep the handlers deterministic, the current time and the generation of ids can be provided by
+//! the context, by implementing [Clock] and [IdGenerator]. In tests, a [ManualClock] and a
+//! [SequentialIdGenerator] make timestamps and ids reproducible.
+//!
+//! ## Validation
+//!
+//! Before being handled, every command is [validated](Validate). When deriving [Command], the
+//! validation can be declared with rules on the fields of the command.
+//!
+//! ## Authorization
+//!
+//! A command can be executed on behalf of a [Principal]. Each command is then checked against the
+//! roles required by its [handler](CommandHandler) and the [policies](Policy) of the command bus
+//! before being handled.
+//!
+//! ## Retries
+//!
+//! Handlers that fail with transient errors can be retried according to a [RetryPolicy], configured
+//! for every handler or for specific ones.
+//!
+//! ## Dead letters
+//!
+//! When a non-critical event handler fails, the event can be saved as a [DeadLetter] in a
+//! [DeadLetterStore] instead of stopping the execution. Dead letters can later be re-driven through
+//! their handler.
+//!
+//! ## Sagas
+//!
+//! A [Saga] coordinates a long-running business process: it keeps a state between events, persisted
+//! by the context through a [SagaStore], and issues commands as the process progresses.
+//!
+//! ## Scheduled commands
+//!
+//! Event handlers can [schedule](Schedule) a command for a later time, or [cancel](CancelSchedule) a
+//! pending one. Scheduled commands are kept by the context in a [ScheduleStore] and executed when
+//! due, according to the [Clock] of the context.
+//!
+//! ## Publishing events
+//!
+//! [Event publishers](EventPublisher) forward the written events to message brokers, on topics that
+//! are named after the events by default. The [InProcessBroker] can stand in for an actual broker in
+//! tests.
+//!
+//! ## Event flow
+//!
+//! The [flow](EventFlow) of commands and events between the handlers of a [Configuration] can be
+//! rendered as a Graphviz or Mermaid diagram. Handlers can declare the events they
+//! [emit](CommandHandler::emits) and the commands they [issue](EventHandler::issues) to complete
+//! the diagram. In debug builds, the command bus can
+//! [verify](Configuration::verify_declarations) that handlers respect their declarations.
+//!
+//! ## Middlewares
+//!
+//! [Event middlewares](EventMiddleware) intercept the writing of each event and its handling by each
+//! event handler. They can be used to enrich events with metadata, to filter events, or to measure
+//! the execution of event handlers. The [CatchPanics] middleware isolates the panics of event
+//! handlers, which otherwise unwind through the [CommandBus].
+//!
 //! ## Features
 //!
 //! The `derive` feature, which is enabled by default, provides derive macros for [Event],
-//! [AggregateEvent] and [Command], as well as attribute macros to easily create
-//! [command handlers](CommandHandler) and [event handlers](EventHandler).
+//! [AggregateEvent], [Command], [SerializableCommand] and [Query], as well as attribute macros to easily create
+//! [command handlers](CommandHandler), [event handlers](EventHandler) and
+//! [query handlers](QueryHandler).
+//!
+//! The `testing` feature provides the [testing] module, with utilities to test handlers. The
+//! `proptest` feature adds property-based testing to this module, using
+//! [proptest](https://docs.rs/proptest).
+//!
+//! The `tracing` feature instruments the [CommandBus] with [tracing](https://docs.rs/tracing)
+//! spans: one for each execution, one for each command, recording its handler, the number of
+//! returned events and the cascade that issued it, and one for each event handler. Failures are
+//! recorded on the spans, and reported with an error event.
+//!
+//! The `metrics` feature records metrics of the [CommandBus] with the
+//! [metrics](https://docs.rs/metrics) facade: counters of executed and failed commands and of
+//! written events, and histograms of the durations of handlers and of the sizes of cascades.
+//!
+//! The `axum` feature provides a [command_router], exposing the
+//! [serializable commands](SerializableCommand) of a [CommandBus] through HTTP with
+//! [axum](https://docs.rs/axum). With [command_router_with_principal], the commands are executed on
+//! behalf of a [Principal] extracted from the requests.
+//!
+//! The `subscriptions` feature allows to [subscribe](CommandBus::subscribe) to the events written
+//! by the [CommandBus], as an async [Subscription] stream.
+//!
+//! The `schema` feature exports the [JSON schemas](Configuration::schema) of the commands and
+//! events of a configuration, using [schemars](https://docs.rs/schemars). The derive macros
+//! implement `JsonSchema` when given `#[presage(schema)]`.
 
 #![forbid(unsafe_code)]
 #![deny(missing_docs)]
 #![cfg_attr(__docs, feature(doc_auto_cfg))]
 
 mod aggregate;
+mod authorization;
+mod clock;
 mod command;
 mod command_bus;
 mod configuration;
+mod dead_letter;
 mod error;
 mod event;
+mod flow;
+#[cfg(feature = "axum")]
+mod gateway;
+mod id_generator;
+mod instrumentation;
+mod middleware;
+mod publisher;
+mod query;
+mod retry;
+mod saga;
+mod schedule;
+mod schema;
+#[cfg(feature = "subscriptions")]
+mod subscription;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod validation;
 
 pub use aggregate::{Aggregate, Id};
-pub use command::{BoxedCommand, Command, CommandHandler, Commands};
+pub use authorization::{Policy, Principal, PrincipalKind};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use command::{
+    BoxedCommand, Command, CommandHandler, Commands, SerializableCommand, SerializedCommand,
+};
 pub use command_bus::{CommandBus, EventWriter};
 pub use configuration::Configuration;
-pub use error::Error;
+pub use dead_letter::{DeadLetter, DeadLetterStore, FileDeadLetterStore, InMemoryDeadLetterStore};
+pub use error::{Compensation, Error, ExecutionError};
 pub use event::{AggregateEvent, Event, EventHandler, Events, SerializedEvent};
+pub use flow::{EventFlow, HandlerFlow};
+#[cfg(feature = "axum")]
+pub use gateway::{command_router, command_router_with_principal, ErrorStatus, ExecutionReport};
+pub use id_generator::{IdGenerator, SequentialIdGenerator};
+pub use middleware::{CatchPanics, EventMiddleware, HandleNext, WriteNext};
+pub use publisher::{EventPublisher, InProcessBroker};
+pub use query::{BoxedOutput, BoxedQuery, Query, QueryBus, QueryHandler};
+pub use retry::{Backoff, RetryPolicy, Sleep};
+pub use saga::{InMemorySagaStore, Saga, SagaStore};
+pub use schedule::{
+    CancelSchedule, InMemoryScheduleStore, Schedule, ScheduleStore, ScheduledCommand,
+};
+#[cfg(feature = "subscriptions")]
+pub use subscription::Subscription;
+pub use validation::{FieldError, Validate, ValidationError};
 
 #[cfg(feature = "derive")]
-pub use presage_macros::{command_handler, event_handler, AggregateEvent, Command, Event};
+pub use presage_macros::{
+    command_handler, event_handler, query_handler, saga, AggregateEvent, Command, Event, Query,
+    SerializableCommand,
+};
 
 #[cfg(feature = "derive")]
 #[doc(hidden)]
 pub use async_trait::async_trait;
+
+/// Re-export of [schemars](https://docs.rs/schemars), used by the derive macros to implement
+/// `JsonSchema`.
+#[cfg(feature = "schema")]
+pub use schemars;
+
+#[cfg(test)]
+extern crate self as presage;
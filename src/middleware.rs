@@ -0,0 +1,371 @@
+use async_trait::async_trait;
+use std::any::Any;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::{Commands, Error, EventHandler, SerializedEvent};
+
+/// Intercepts the persistence and the handling of [events](crate::Event) by a
+/// [CommandBus](crate::CommandBus).
+///
+/// Each method receives the next step of the pipeline, which is either the next middleware or the
+/// actual operation. A middleware can act before and after running the next step, modify the event
+/// before it is written, or skip the next step entirely. Middlewares are run in the order in which
+/// they have been added to the [Configuration](crate::Configuration).
+///
+/// Both methods have a default implementation that simply runs the next step, so only the relevant
+/// one needs to be implemented.
+///
+/// # Type arguments
+///
+/// * `C` - the context for this middleware
+/// * `E` - the type of errors returned if the middleware fails
+///
+/// # Example
+///
+/// ```
+/// use presage::{async_trait, Commands, EventMiddleware, HandleNext, SerializedEvent};
+///
+/// struct SkipInMaintenance;
+///
+/// struct Context {
+///     maintenance: bool,
+/// }
+///
+/// #[async_trait]
+/// impl<E> EventMiddleware<Context, E> for SkipInMaintenance {
+///     async fn handle(
+///         &self,
+///         context: &mut Context,
+///         event: &SerializedEvent,
+///         next: HandleNext<'_, Context, E>,
+///     ) -> Result<Commands, E> {
+///         if context.maintenance {
+///             Ok(Commands::new())
+///         } else {
+///             next.run(context, event).await
+///         }
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait EventMiddleware<C, E>: Send + Sync {
+    /// Intercepts the writing of an event by the [EventWriter](crate::EventWriter). Since the event
+    /// is mutably borrowed, it can be modified (e.g., enriched with metadata) before being written.
+    /// The modified event is also the one passed to the event handlers.
+    ///
+    /// Skipping the next step prevents the event from being written, but it is still handled.
+    async fn write(
+        &self,
+        context: &mut C,
+        event: &mut SerializedEvent,
+        next: WriteNext<'_, C, E>,
+    ) -> Result<(), E>
+    where
+        C: Send,
+    {
+        next.run(context, event).await
+    }
+
+    /// Intercepts the handling of an event by an [event handler](EventHandler). The middleware is
+    /// called once for each event handler of the event.
+    ///
+    /// Skipping the next step prevents the event handler from being executed.
+    async fn handle(
+        &self,
+        context: &mut C,
+        event: &SerializedEvent,
+        next: HandleNext<'_, C, E>,
+    ) -> Result<Commands, E>
+    where
+        C: Send,
+    {
+        next.run(context, event).await
+    }
+}
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub(crate) type WriteFn<C, E> =
+    for<'a> fn(&'a mut C, &'a SerializedEvent) -> BoxFuture<'a, Result<(), E>>;
+
+/// The remaining steps for writing an event: the next [middlewares](EventMiddleware), then the
+/// [EventWriter](crate::EventWriter).
+pub struct WriteNext<'a, C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    middlewares: &'a [&'static dyn EventMiddleware<C, E>],
+    write: WriteFn<C, E>,
+}
+
+impl<'a, C, E> WriteNext<'a, C, E> {
+    pub(crate) fn new(
+        middlewares: &'a [&'static dyn EventMiddleware<C, E>],
+        write: WriteFn<C, E>,
+    ) -> Self {
+        Self { middlewares, write }
+    }
+
+    /// Runs the next step.
+    pub async fn run(self, context: &mut C, event: &mut SerializedEvent) -> Result<(), E>
+    where
+        C: Send,
+    {
+        match self.middlewares.split_first() {
+            Some((middleware, middlewares)) => {
+                middleware
+                    .write(context, event, WriteNext::new(middlewares, self.write))
+                    .await
+            }
+            None => (self.write)(context, event).await,
+        }
+    }
+}
+
+/// The remaining steps for handling an event: the next [middlewares](EventMiddleware), then the
+/// [event handler](EventHandler).
+pub struct HandleNext<'a, C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    middlewares: &'a [&'static dyn EventMiddleware<C, E>],
+    handler: &'static dyn EventHandler<C, E>,
+}
+
+impl<'a, C, E> HandleNext<'a, C, E> {
+    pub(crate) fn new(
+        middlewares: &'a [&'static dyn EventMiddleware<C, E>],
+        handler: &'static dyn EventHandler<C, E>,
+    ) -> Self {
+        Self {
+            middlewares,
+            handler,
+        }
+    }
+
+    /// The event handler that will handle the event at the end of the pipeline.
+    pub fn handler(&self) -> &'static dyn EventHandler<C, E> {
+        self.handler
+    }
+
+    /// Runs the next step.
+    pub async fn run(self, context: &mut C, event: &SerializedEvent) -> Result<Commands, E>
+    where
+        C: Send,
+    {
+        match self.middlewares.split_first() {
+            Some((middleware, middlewares)) => {
+                middleware
+                    .handle(context, event, HandleNext::new(middlewares, self.handler))
+                    .await
+            }
+            None => self.handler.handle(context, event).await,
+        }
+    }
+}
+
+/// An [EventMiddleware] that catches the panics of event handlers, and turns them into
+/// [Error::HandlerPanicked] errors. A panicking handler then fails like any other: its failure
+/// stops the execution, or is saved as a [dead letter](crate::DeadLetter) for non-critical
+/// handlers.
+///
+/// Without this middleware, the panic of a handler unwinds through the
+/// [CommandBus](crate::CommandBus) up to its caller. Panics of command handlers, of event writers,
+/// and of the middlewares added before this one are never caught. Since the handler is interrupted,
+/// it may leave the context in an inconsistent state.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CatchPanics;
+
+#[async_trait]
+impl<C, E> EventMiddleware<C, E> for CatchPanics
+where
+    E: From<Error>,
+{
+    async fn handle(
+        &self,
+        context: &mut C,
+        event: &SerializedEvent,
+        next: HandleNext<'_, C, E>,
+    ) -> Result<Commands, E>
+    where
+        C: Send,
+    {
+        let handler = next.handler().name();
+        CatchUnwind(Box::pin(next.run(context, event)))
+            .await
+            .unwrap_or_else(|panic| {
+                Err(Error::HandlerPanicked(handler, panic_message(panic)).into())
+            })
+    }
+}
+
+/// A future that catches the panics of the wrapped future.
+struct CatchUnwind<'a, T>(BoxFuture<'a, T>);
+
+impl<T> Future for CatchUnwind<'_, T> {
+    type Output = std::thread::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.0.as_mut().poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, commands, event_handler, events, Command, CommandBus, Configuration,
+        Error, Event, EventWriter, Events,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[tokio::test]
+    async fn test_write_middleware_enriches_events() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .event_middleware(&AddTenant)
+                .command_handler(&create_item),
+        );
+        let mut context = TestContext::default();
+
+        command_bus.execute(&mut context, CreateItem).await.unwrap();
+
+        assert_eq!(context.written.len(), 1);
+        assert_eq!(context.written[0].metadata()["tenant"], "acme");
+    }
+
+    #[tokio::test]
+    async fn test_handle_middlewares_are_run_in_order() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .event_middleware(&SkipHandlers)
+                .event_middleware(&AddTenant)
+                .command_handler(&create_item)
+                .event_handler(&on_item_created),
+        );
+        let mut context = TestContext::default();
+
+        command_bus.execute(&mut context, CreateItem).await.unwrap();
+        assert_eq!(context.handled, vec!["acme"]);
+
+        context.skip = true;
+        command_bus.execute(&mut context, CreateItem).await.unwrap();
+        assert_eq!(context.handled, vec!["acme"]);
+        assert_eq!(context.written.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_handler_panics_are_caught() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .event_middleware(&CatchPanics)
+                .command_handler(&create_item)
+                .event_handler(&panic_on_item_created),
+        );
+
+        let error = command_bus
+            .execute(&mut TestContext::default(), CreateItem)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.error(),
+            Error::HandlerPanicked("panic_on_item_created", message) if message == "out of stock"
+        ));
+        assert_eq!(error.handler(), Some("panic_on_item_created"));
+    }
+
+    #[derive(Default)]
+    struct TestContext {
+        written: Vec<SerializedEvent>,
+        handled: Vec<String>,
+        skip: bool,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, event: &SerializedEvent) -> Result<(), Error> {
+            self.written.push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CreateItem;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated;
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated))
+    }
+
+    #[event_handler(events = [ItemCreated])]
+    async fn on_item_created(
+        context: &mut TestContext,
+        event: &SerializedEvent,
+    ) -> Result<Commands, Error> {
+        let tenant = event.metadata()["tenant"].as_str().unwrap_or_default();
+        context.handled.push(tenant.to_string());
+        Ok(commands!())
+    }
+
+    #[event_handler]
+    async fn panic_on_item_created(_: &mut TestContext, _: ItemCreated) -> Result<Commands, Error> {
+        panic!("out of stock")
+    }
+
+    struct AddTenant;
+
+    #[async_trait]
+    impl EventMiddleware<TestContext, Error> for AddTenant {
+        async fn write(
+            &self,
+            context: &mut TestContext,
+            event: &mut SerializedEvent,
+            next: WriteNext<'_, TestContext, Error>,
+        ) -> Result<(), Error> {
+            event.metadata_mut().insert("tenant".into(), "acme".into());
+            next.run(context, event).await
+        }
+    }
+
+    struct SkipHandlers;
+
+    #[async_trait]
+    impl EventMiddleware<TestContext, Error> for SkipHandlers {
+        async fn handle(
+            &self,
+            context: &mut TestContext,
+            event: &SerializedEvent,
+            next: HandleNext<'_, TestContext, Error>,
+        ) -> Result<Commands, Error> {
+            if context.skip {
+                Ok(commands!())
+            } else {
+                next.run(context, event).await
+            }
+        }
+    }
+}
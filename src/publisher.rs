@@ -0,0 +1,227 @@
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+use crate::{Error, SerializedEvent};
+
+/// Forwards the events written by a [CommandBus](crate::CommandBus) to a message broker.
+///
+/// Events are published after they have been successfully written, on the topic configured with
+/// [Configuration::topic](crate::Configuration::topic), or on a topic named after the event by
+/// default. A failure to publish an event stops the execution: since the event is already written,
+/// it is then neither handled nor published again. Events are thus forwarded at most once, and an
+/// event whose publishing failed must be published again with
+/// [CommandBus::publish](crate::CommandBus::publish).
+///
+/// Présage provides an [in-process broker](InProcessBroker), which can stand in for an actual
+/// broker in tests.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Publishes an event on the given topic.
+    async fn publish(&self, topic: &str, event: &SerializedEvent) -> Result<(), Error>;
+}
+
+/// An in-process [EventPublisher], with topics and consumer groups.
+///
+/// Each topic keeps all the events published on it, in order. Consumers are organized in groups:
+/// each group has its own position in each topic, so that every group receives every event of the
+/// topic, while the consumers of a same group share the events.
+///
+/// # Example
+///
+/// ```
+/// use presage::{Event, EventPublisher, InProcessBroker};
+/// # #[derive(Event, serde::Serialize, serde::Deserialize)]
+/// # struct TodoCreated;
+///
+/// static BROKER: InProcessBroker = InProcessBroker::new();
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), presage::Error> {
+/// BROKER.publish("todos", &TodoCreated.serialize()?).await?;
+///
+/// assert_eq!(BROKER.poll("todos", "notifications", 10)?.len(), 1);
+/// assert_eq!(BROKER.poll("todos", "notifications", 10)?.len(), 0);
+/// assert_eq!(BROKER.poll("todos", "audit", 10)?.len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct InProcessBroker {
+    topics: Mutex<BTreeMap<String, Topic>>,
+}
+
+#[derive(Debug, Default)]
+struct Topic {
+    events: Vec<SerializedEvent>,
+    offsets: BTreeMap<String, usize>,
+}
+
+impl InProcessBroker {
+    /// Creates a new broker without any topic.
+    pub const fn new() -> Self {
+        Self {
+            topics: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Lists the topics on which events were published.
+    pub fn topics(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .topics
+            .lock()
+            .map_err(broker_error)?
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    /// Lists all the events published on a topic, in the order in which they were published.
+    pub fn events(&self, topic: &str) -> Result<Vec<SerializedEvent>, Error> {
+        let topics = self.topics.lock().map_err(broker_error)?;
+        Ok(topics
+            .get(topic)
+            .map(|topic| topic.events.clone())
+            .unwrap_or_default())
+    }
+
+    /// Consumes at most `max` events of a topic on behalf of a consumer group. The returned events
+    /// are the next ones the group has not consumed yet, and the position of the group in the topic
+    /// is moved after them.
+    pub fn poll(
+        &self,
+        topic: &str,
+        group: &str,
+        max: usize,
+    ) -> Result<Vec<SerializedEvent>, Error> {
+        let mut topics = self.topics.lock().map_err(broker_error)?;
+        let Some(topic) = topics.get_mut(topic) else {
+            return Ok(Vec::new());
+        };
+        let offset = topic.offsets.entry(group.to_string()).or_default();
+        let events: Vec<_> = topic
+            .events
+            .iter()
+            .skip(*offset)
+            .take(max)
+            .cloned()
+            .collect();
+        *offset += events.len();
+        Ok(events)
+    }
+
+    /// The number of events of a topic that a consumer group has not consumed yet.
+    pub fn lag(&self, topic: &str, group: &str) -> Result<usize, Error> {
+        let topics = self.topics.lock().map_err(broker_error)?;
+        Ok(topics.get(topic).map_or(0, |topic| {
+            topic.events.len() - topic.offsets.get(group).copied().unwrap_or_default()
+        }))
+    }
+}
+
+#[async_trait]
+impl EventPublisher for InProcessBroker {
+    async fn publish(&self, topic: &str, event: &SerializedEvent) -> Result<(), Error> {
+        let mut topics = self.topics.lock().map_err(broker_error)?;
+        topics
+            .entry(topic.to_string())
+            .or_default()
+            .events
+            .push(event.clone());
+        Ok(())
+    }
+}
+
+fn broker_error(error: impl Display) -> Error {
+    Error::EventPublisherError(error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{command_handler, events, CommandBus, Configuration, Event, EventWriter, Events};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn test_events_are_published_on_their_topic() {
+        static BROKER: InProcessBroker = InProcessBroker::new();
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&ship_order)
+                .event_publisher(&BROKER)
+                .topic::<OrderShipped>("orders"),
+        );
+
+        command_bus.execute(&mut Orders, ShipOrder).await.unwrap();
+
+        assert_eq!(BROKER.topics().unwrap(), ["orders", "parcel-sent"]);
+        let shipped = BROKER.poll("orders", "billing", 10).unwrap();
+        assert_eq!(shipped.len(), 1);
+        assert_eq!(shipped[0].name(), OrderShipped::NAME);
+        assert_eq!(BROKER.lag("orders", "billing").unwrap(), 0);
+        assert_eq!(BROKER.lag("orders", "analytics").unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_events_can_be_published_again_after_a_failure() {
+        static BROKER: InProcessBroker = InProcessBroker::new();
+        static PUBLISHER: FailingOnce = FailingOnce(AtomicBool::new(false), &BROKER);
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&ship_order)
+                .event_publisher(&PUBLISHER)
+                .topic::<OrderShipped>("orders"),
+        );
+
+        let error = command_bus
+            .execute(&mut Orders, ShipOrder)
+            .await
+            .unwrap_err();
+        command_bus
+            .publish(&OrderShipped.serialize().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(error.event(), Some("order-shipped"));
+        assert_eq!(BROKER.topics().unwrap(), ["orders"]);
+    }
+
+    struct FailingOnce(AtomicBool, &'static InProcessBroker);
+
+    #[async_trait]
+    impl EventPublisher for FailingOnce {
+        async fn publish(&self, topic: &str, event: &SerializedEvent) -> Result<(), Error> {
+            if self.0.swap(true, Ordering::Relaxed) {
+                self.1.publish(topic, event).await
+            } else {
+                Err(Error::EventPublisherError("unavailable".to_string()))
+            }
+        }
+    }
+
+    struct Orders;
+
+    #[async_trait]
+    impl EventWriter for Orders {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(crate::Command)]
+    struct ShipOrder;
+
+    #[derive(crate::Event, serde::Serialize, serde::Deserialize)]
+    struct OrderShipped;
+
+    #[derive(crate::Event, serde::Serialize, serde::Deserialize)]
+    struct ParcelSent;
+
+    #[command_handler]
+    async fn ship_order(_: &mut Orders, _: ShipOrder) -> Result<Events, Error> {
+        Ok(events!(OrderShipped, ParcelSent))
+    }
+}
@@ -0,0 +1,257 @@
+use async_trait::async_trait;
+use std::any::{type_name, Any};
+use std::collections::HashMap;
+
+use crate::{Configuration, Error};
+
+/// A request to read the state of the system, without modifying it.
+///
+/// A corresponding [query handler](QueryHandler) must be defined, and the query is executed by a
+/// [QueryBus].
+///
+/// # Associated constant and type
+///
+/// * [NAME](Self::NAME) - the unique name of the query
+/// * [Output](Self::Output) - the type of the result of the query
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Debug)]
+/// # pub struct Todo;
+/// pub struct ListTodos {
+///     pub include_archived: bool,
+/// }
+///
+/// impl presage::Query for ListTodos {
+///     const NAME: &'static str = "list-todos";
+///     type Output = Vec<Todo>;
+/// }
+/// ```
+pub trait Query: Send + Sync + 'static {
+    /// The name of the query. Must be unique.
+    const NAME: &'static str;
+
+    /// The type of the result of the query.
+    type Output: Send + 'static;
+}
+
+/// A query that has been boxed to be dispatched.
+///
+/// Can be created from a [Query].
+#[derive(Debug)]
+pub struct BoxedQuery {
+    name: &'static str,
+    query: Box<dyn Any + Send + Sync>,
+}
+
+impl BoxedQuery {
+    /// Returns the name of the boxed query.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Tries to downcast the boxed query to a concrete [Query] implementation.
+    pub fn downcast<Q: Query>(self) -> Result<Q, Error> {
+        self.query
+            .downcast()
+            .map(|query| *query)
+            .map_err(|_| Error::QueryDowncastError(type_name::<Q>()))
+    }
+}
+
+impl<Q: Query> From<Q> for BoxedQuery {
+    fn from(query: Q) -> Self {
+        BoxedQuery {
+            name: Q::NAME,
+            query: Box::new(query),
+        }
+    }
+}
+
+/// The result of a [query handler](QueryHandler), boxed to be returned by the [QueryBus].
+#[derive(Debug)]
+pub struct BoxedOutput(Box<dyn Any + Send>);
+
+impl BoxedOutput {
+    /// Boxes the result of a query.
+    pub fn new<T: Send + 'static>(output: T) -> Self {
+        Self(Box::new(output))
+    }
+
+    /// Tries to downcast the boxed result to the [output](Query::Output) of the query `Q`.
+    pub fn downcast<Q: Query>(self) -> Result<Q::Output, Error> {
+        self.0
+            .downcast()
+            .map(|output| *output)
+            .map_err(|_| Error::QueryDowncastError(type_name::<Q::Output>()))
+    }
+}
+
+/// A handler of a specific [Query], reading the state of the system through a shared context.
+///
+/// # Type arguments
+///
+/// * `C` - the context of the handler
+/// * `E` - the type of errors returned by the handler
+///
+/// Query handlers can be easily created using the `query_handler` macro:
+///
+/// ```
+/// # use presage::{query_handler, Error, Query};
+/// # #[derive(Clone)]
+/// # pub struct Todo;
+/// # pub struct Todos(Vec<Todo>);
+/// #[derive(Query)]
+/// #[presage(output = Vec<Todo>)]
+/// pub struct ListTodos;
+///
+/// #[query_handler]
+/// async fn list_todos(context: &Todos, _: ListTodos) -> Result<Vec<Todo>, Error> {
+///     Ok(context.0.clone())
+/// }
+/// ```
+///
+/// The result of the handler must be the output of the query, which is checked at compile time:
+///
+/// ```compile_fail
+/// # use presage::{query_handler, Error, Query};
+/// #[derive(Query)]
+/// #[presage(output = usize)]
+/// pub struct CountTodos;
+///
+/// #[query_handler]
+/// async fn count_todos(_: &(), _: CountTodos) -> Result<u32, Error> {
+///     Ok(0)
+/// }
+/// ```
+#[async_trait]
+pub trait QueryHandler<C, E>: Send + Sync {
+    /// The name of the handler. By default, the name of the type implementing the trait.
+    fn name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+
+    /// The name of the handled query.
+    fn query_name(&self) -> &'static str;
+
+    /// Handles the query, and returns its boxed result.
+    async fn handle(&self, context: &C, query: BoxedQuery) -> Result<BoxedOutput, E>;
+}
+
+/// Executes a [query](Query) with its [handler](QueryHandler), within a shared context.
+///
+/// Unlike the [CommandBus](crate::CommandBus), the context is not mutably borrowed, so queries can
+/// be executed concurrently, and no event is issued.
+///
+/// Can be created using [new()](QueryBus::new) or the [Default] implementation.
+///
+/// # Example
+///
+/// ```
+/// # use presage::{query_handler, Configuration, Error, Query, QueryBus};
+/// #[derive(Query)]
+/// #[presage(output = usize)]
+/// struct CountTodos;
+///
+/// #[query_handler]
+/// async fn count_todos(context: &Vec<String>, _: CountTodos) -> Result<usize, Error> {
+///     Ok(context.len())
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Error> {
+/// let query_bus = QueryBus::new().configure(Configuration::new().query_handler(&count_todos));
+/// let todos = vec!["Write documentation".to_string()];
+///
+/// assert_eq!(query_bus.execute(&todos, CountTodos).await?, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryBus<C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    query_handlers: HashMap<&'static str, &'static dyn QueryHandler<C, E>>,
+}
+
+impl<C, E> Default for QueryBus<C, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, E> QueryBus<C, E> {
+    /// Creates a new, empty, [QueryBus]
+    pub fn new() -> Self {
+        Self {
+            query_handlers: Default::default(),
+        }
+    }
+
+    /// Configures the query bus using the [query handlers](Configuration::query_handler) of the
+    /// specified configuration. Takes ownership of `self` and returns it to allow chaining.
+    pub fn configure(mut self, configuration: Configuration<C, E>) -> Self {
+        self.query_handlers.extend(configuration.query_handlers);
+        self
+    }
+}
+
+impl<C, E> QueryBus<C, E>
+where
+    C: Sync,
+    E: From<Error>,
+{
+    /// Executes a [query](Query) with the provided context, and returns its result.
+    pub async fn execute<Q>(&self, context: &C, query: Q) -> Result<Q::Output, E>
+    where
+        Q: Query,
+    {
+        let handler = self
+            .query_handlers
+            .get(Q::NAME)
+            .ok_or(Error::MissingQueryHandler(Q::NAME))?;
+        let output = handler.handle(context, query.into()).await?;
+        Ok(output.downcast::<Q>()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query_handler;
+
+    #[tokio::test]
+    async fn test_execute() -> Result<(), Error> {
+        let query_bus = QueryBus::new().configure(Configuration::new().query_handler(&get_stock));
+
+        let stock = query_bus.execute(&Inventory(3), GetStock).await?;
+
+        assert_eq!(stock, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_query_handler() {
+        let query_bus: QueryBus<Inventory, Error> = QueryBus::new();
+
+        let result = query_bus.execute(&Inventory(3), GetStock).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::MissingQueryHandler("get-stock"))
+        ));
+    }
+
+    struct Inventory(u32);
+
+    #[derive(crate::Query)]
+    #[presage(output = u32)]
+    struct GetStock;
+
+    #[query_handler]
+    async fn get_stock(context: &Inventory, _: GetStock) -> Result<u32, Error> {
+        Ok(context.0)
+    }
+}
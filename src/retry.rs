@@ -0,0 +1,459 @@
+use async_trait::async_trait;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Waits for a given duration between two attempts of a [RetryPolicy], and draws the random
+/// numbers used for the jitter of its [Backoff].
+///
+/// Présage does not depend on any async runtime, so the actual implementation must be provided
+/// (e.g., with `tokio::time::sleep`). In tests, an implementation that only records the durations,
+/// and returns a fixed random number, keeps the execution deterministic.
+///
+/// # Example
+///
+/// ```
+/// use presage::{async_trait, Sleep};
+/// use std::time::Duration;
+///
+/// struct TokioSleep;
+///
+/// #[async_trait]
+/// impl Sleep for TokioSleep {
+///     async fn sleep(&self, duration: Duration) {
+///         tokio::time::sleep(duration).await
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Sleep: Send + Sync {
+    /// Waits for the given duration.
+    async fn sleep(&self, duration: Duration);
+
+    /// Returns a random number between 0 (inclusive) and 1 (exclusive). By default, the number is
+    /// derived from the random keys of the standard library's hash maps, which cannot be seeded.
+    fn random(&self) -> f64 {
+        let value = RandomState::new().build_hasher().finish();
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// An exponential backoff between the attempts of a [RetryPolicy].
+///
+/// The delay before the n-th retry is `initial_delay * multiplier^(n - 1)`, capped at `max_delay`.
+/// With a jitter `j` (between 0 and 1), the delay is randomly reduced by up to `j` times its value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Backoff {
+    initial_delay: Duration,
+    multiplier: u32,
+    max_delay: Duration,
+    jitter: f64,
+}
+
+impl Backoff {
+    /// Creates a new exponential backoff, starting at the given delay. By default, the delay is
+    /// doubled after each attempt, is not capped, and has no jitter.
+    pub const fn exponential(initial_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            multiplier: 2,
+            max_delay: Duration::MAX,
+            jitter: 0.0,
+        }
+    }
+
+    /// Sets the multiplier applied to the delay after each attempt.
+    pub const fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Sets the maximum delay between two attempts.
+    pub const fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the jitter, which is clamped between 0 (no jitter) and 1 (full jitter).
+    pub const fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The delay before the given retry (starting at 1), without jitter.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(Duration::MAX)
+            .min(self.max_delay)
+    }
+
+    /// The delay before the given retry (starting at 1), reduced by the jitter according to the
+    /// given random number (between 0 and 1).
+    pub fn jittered_delay(&self, retry: u32, random: f64) -> Duration {
+        let delay = self.delay(retry);
+        if self.jitter > 0.0 {
+            delay.mul_f64(1.0 - self.jitter * random.clamp(0.0, 1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::exponential(Duration::ZERO)
+    }
+}
+
+/// Defines how a failing handler is retried by the [CommandBus](crate::CommandBus).
+///
+/// A policy has a maximum number of attempts (including the first one), a [Backoff] between the
+/// attempts, and a predicate that decides if an error is transient. The policy can be applied to
+/// every handler or to specific handlers, using a [Configuration](crate::Configuration).
+///
+/// Event handlers can always be retried. Command handlers can only be retried if their command can
+/// be copied (see [Command::try_clone](crate::Command::try_clone)).
+///
+/// # Example
+///
+/// ```
+/// use presage::{Backoff, RetryPolicy};
+/// use std::time::Duration;
+///
+/// # use presage::{async_trait, Sleep};
+/// # struct TokioSleep;
+/// # #[async_trait]
+/// # impl Sleep for TokioSleep {
+/// #     async fn sleep(&self, duration: Duration) {}
+/// # }
+/// # struct MyError { transient: bool }
+/// static RETRY: RetryPolicy<MyError> = RetryPolicy::new(3)
+///     .backoff(
+///         Backoff::exponential(Duration::from_millis(100)).jitter(0.5),
+///         &TokioSleep,
+///     )
+///     .retry_if(|error| error.transient);
+/// ```
+pub struct RetryPolicy<E> {
+    max_attempts: u32,
+    backoff: Backoff,
+    retryable: fn(&E) -> bool,
+    sleep: Option<&'static dyn Sleep>,
+}
+
+impl<E> RetryPolicy<E> {
+    /// Creates a new policy with the maximum number of attempts. By default, there is no delay
+    /// between attempts and all errors are retried.
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::exponential(Duration::ZERO),
+            retryable: |_| true,
+            sleep: None,
+        }
+    }
+
+    /// Sets the backoff between attempts, and how to wait between them.
+    pub const fn backoff(mut self, backoff: Backoff, sleep: &'static dyn Sleep) -> Self {
+        self.backoff = backoff;
+        self.sleep = Some(sleep);
+        self
+    }
+
+    /// Sets the predicate that decides if an error is transient and the handler can be retried.
+    pub const fn retry_if(mut self, retryable: fn(&E) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// The maximum number of attempts, including the first one.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns `true` if a handler that failed with the given error at the given attempt (starting
+    /// at 1) must be retried.
+    pub fn should_retry(&self, attempt: u32, error: &E) -> bool {
+        attempt < self.max_attempts && (self.retryable)(error)
+    }
+
+    pub(crate) async fn wait(&self, attempt: u32) {
+        if let Some(sleep) = self.sleep {
+            let delay = self.backoff.jittered_delay(attempt, sleep.random());
+            sleep.sleep(delay).await
+        }
+    }
+}
+
+impl<E> Clone for RetryPolicy<E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for RetryPolicy<E> {}
+
+impl<E> Debug for RetryPolicy<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, event_handler, events, Command, CommandBus, Commands, Configuration,
+        Error, Event, EventWriter, Events, SerializedEvent,
+    };
+    use serde::{Deserialize, Serialize};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let backoff = Backoff::exponential(Duration::from_millis(100))
+            .multiplier(3)
+            .max_delay(Duration::from_secs(1));
+
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(300));
+        assert_eq!(backoff.delay(3), Duration::from_millis(900));
+        assert_eq!(backoff.delay(4), Duration::from_secs(1));
+        assert_eq!(backoff.delay(100), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter_reduces_the_delay() {
+        let backoff = Backoff::exponential(Duration::from_secs(1)).jitter(0.5);
+
+        assert_eq!(backoff.jittered_delay(1, 0.0), Duration::from_secs(1));
+        assert_eq!(backoff.jittered_delay(1, 0.5), Duration::from_millis(750));
+        assert_eq!(backoff.jittered_delay(1, 1.0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_default_random_is_between_zero_and_one() {
+        let random = NoSleep.random();
+
+        assert!((0.0..1.0).contains(&random));
+    }
+
+    #[tokio::test]
+    async fn test_command_handler_is_retried() {
+        static SLEEP: RecordingSleep = RecordingSleep::new();
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&flaky_command)
+                .retry_policy(RetryPolicy::new(3).backoff(
+                    Backoff::exponential(Duration::from_secs(1)).jitter(0.5),
+                    &SLEEP,
+                )),
+        );
+        let mut context = TestContext::failing(2);
+
+        command_bus
+            .execute(&mut context, FlakyCommand)
+            .await
+            .unwrap();
+
+        assert_eq!(context.attempts, 3);
+        assert_eq!(
+            *SLEEP.0.lock().unwrap(),
+            vec![Duration::from_millis(750), Duration::from_millis(1500)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_handler_fails_after_max_attempts() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new().command_handler_with_retry(&flaky_command, RetryPolicy::new(2)),
+        );
+        let mut context = TestContext::failing(2);
+
+        let result = command_bus.execute(&mut context, FlakyCommand).await;
+
+        assert!(result.is_err());
+        assert_eq!(context.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_errors_are_not_retried() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&flaky_command)
+                .retry_policy(RetryPolicy::new(3).retry_if(|_| false)),
+        );
+        let mut context = TestContext::failing(1);
+
+        let result = command_bus.execute(&mut context, FlakyCommand).await;
+
+        assert!(result.is_err());
+        assert_eq!(context.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_command_without_copy_is_not_retried() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&single_use_command)
+                .retry_policy(RetryPolicy::new(3)),
+        );
+        let mut context = TestContext::failing(1);
+
+        let result = command_bus.execute(&mut context, SingleUseCommand).await;
+
+        assert!(result.is_err());
+        assert_eq!(context.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_handler_is_retried() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .event_handler_with_retry(&flaky_event_handler, RetryPolicy::new(2)),
+        );
+        let mut context = TestContext::failing(1);
+
+        command_bus.execute(&mut context, CreateItem).await.unwrap();
+
+        assert_eq!(context.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_is_specific_to_the_handler() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_item)
+                .event_handler(&flaky_event_handler)
+                .event_handler_with_retry(&other::flaky_event_handler, RetryPolicy::new(3)),
+        );
+        let mut context = TestContext::failing(1);
+
+        let result = command_bus.execute(&mut context, CreateItem).await;
+
+        assert!(result.is_err());
+        assert_eq!(context.attempts, 1);
+    }
+
+    struct RecordingSleep(Mutex<Vec<Duration>>);
+
+    impl RecordingSleep {
+        const fn new() -> Self {
+            Self(Mutex::new(Vec::new()))
+        }
+    }
+
+    #[async_trait]
+    impl Sleep for RecordingSleep {
+        async fn sleep(&self, duration: Duration) {
+            self.0.lock().unwrap().push(duration);
+        }
+
+        fn random(&self) -> f64 {
+            0.5
+        }
+    }
+
+    struct NoSleep;
+
+    #[async_trait]
+    impl Sleep for NoSleep {
+        async fn sleep(&self, _: Duration) {}
+    }
+
+    struct TestContext {
+        failures: i32,
+        attempts: i32,
+    }
+
+    impl TestContext {
+        fn failing(failures: i32) -> Self {
+            Self {
+                failures,
+                attempts: 0,
+            }
+        }
+
+        fn attempt(&mut self) -> Result<(), Error> {
+            self.attempts += 1;
+            if self.attempts <= self.failures {
+                Err(Error::MissingCommandHandler("unavailable"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Command)]
+    #[presage(retryable)]
+    struct FlakyCommand;
+
+    #[command_handler]
+    async fn flaky_command(context: &mut TestContext, _: FlakyCommand) -> Result<Events, Error> {
+        context.attempt()?;
+        Ok(Events::new())
+    }
+
+    #[derive(Command)]
+    struct SingleUseCommand;
+
+    #[command_handler]
+    async fn single_use_command(
+        context: &mut TestContext,
+        _: SingleUseCommand,
+    ) -> Result<Events, Error> {
+        context.attempt()?;
+        Ok(Events::new())
+    }
+
+    #[derive(Command)]
+    struct CreateItem;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemCreated;
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        Ok(events!(ItemCreated))
+    }
+
+    #[event_handler]
+    async fn flaky_event_handler(
+        context: &mut TestContext,
+        _: ItemCreated,
+    ) -> Result<Commands, Error> {
+        context.attempt()?;
+        Ok(Commands::new())
+    }
+
+    mod other {
+        use super::*;
+
+        #[event_handler]
+        pub async fn flaky_event_handler(
+            _: &mut TestContext,
+            _: ItemCreated,
+        ) -> Result<Commands, Error> {
+            Ok(Commands::new())
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::{Commands, Error, EventHandler, SerializedEvent};
+
+/// A long-running business process that reacts to [events](crate::Event) and issues
+/// [commands](crate::Command).
+///
+/// Unlike [event handlers](EventHandler), a saga has a state, which is persisted in a [SagaStore]
+/// between two events. Each instance of a saga is identified by a correlation id, extracted from
+/// the events it handles (e.g., the id of an order).
+///
+/// The lifecycle of a saga is the following:
+/// * a new instance is created, using [Default], when one of the
+///   [starting events](Self::STARTING_EVENTS) is handled and no instance exists for its correlation
+///   id;
+/// * each handled event updates the instance, which is then saved, and may issue commands that are
+///   executed by the [CommandBus](crate::CommandBus);
+/// * once [complete](Self::is_complete), the instance is deleted from the store.
+///
+/// Events that do not start the saga are ignored if there is no instance for their correlation id.
+///
+/// This trait is usually implemented with the `#[saga]` macro. Sagas are added to a
+/// [Configuration](crate::Configuration) with [saga](crate::Configuration::saga).
+pub trait Saga: Default + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// The name of the saga. Must be unique.
+    const NAME: &'static str;
+
+    /// The names of the events handled by the saga.
+    const EVENTS: &'static [&'static str];
+
+    /// The names of the events that start a new instance of the saga.
+    const STARTING_EVENTS: &'static [&'static str];
+
+    /// Extracts the correlation id from an event. Returns `None` if the event is not handled by the
+    /// saga.
+    fn correlation_id(event: &SerializedEvent) -> Result<Option<String>, Error>;
+
+    /// Handles an event, updating the state of the saga, and returns the commands to execute.
+    fn handle(&mut self, event: SerializedEvent) -> Result<Commands, Error>;
+
+    /// Returns `true` if the saga is complete. By default, a saga never completes.
+    fn is_complete(&self) -> bool {
+        false
+    }
+}
+
+/// Persists the state of [sagas](Saga).
+///
+/// It is implemented by the context of the [CommandBus](crate::CommandBus), so that the state of
+/// the sagas can be persisted along with the events (e.g., within the same transaction). States are
+/// identified by the name of the saga and the correlation id of the instance.
+///
+/// # Associated type
+///
+/// * [`Error`](Self::Error) - the type of errors returned if the store fails
+#[async_trait]
+pub trait SagaStore: Send {
+    /// Error returned when the store fails
+    type Error;
+
+    /// Loads the state of an instance of a saga, if it exists.
+    async fn load_saga(
+        &mut self,
+        saga: &'static str,
+        id: &str,
+    ) -> Result<Option<Value>, Self::Error>;
+
+    /// Saves the state of an instance of a saga.
+    async fn save_saga(
+        &mut self,
+        saga: &'static str,
+        id: &str,
+        state: Value,
+    ) -> Result<(), Self::Error>;
+
+    /// Deletes the state of a complete instance of a saga.
+    async fn delete_saga(&mut self, saga: &'static str, id: &str) -> Result<(), Self::Error>;
+}
+
+/// A [SagaStore] that keeps the state of sagas in memory.
+///
+/// It can be used as a context, or embedded in a context that delegates its implementation of
+/// [SagaStore] to it.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySagaStore {
+    states: HashMap<(&'static str, String), Value>,
+}
+
+impl InMemorySagaStore {
+    /// Creates a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of instances of the given saga that are not complete.
+    pub fn count(&self, saga: &str) -> usize {
+        self.states.keys().filter(|(name, _)| *name == saga).count()
+    }
+}
+
+#[async_trait]
+impl SagaStore for InMemorySagaStore {
+    type Error = Error;
+
+    async fn load_saga(&mut self, saga: &'static str, id: &str) -> Result<Option<Value>, Error> {
+        Ok(self.states.get(&(saga, id.to_string())).cloned())
+    }
+
+    async fn save_saga(&mut self, saga: &'static str, id: &str, state: Value) -> Result<(), Error> {
+        self.states.insert((saga, id.to_string()), state);
+        Ok(())
+    }
+
+    async fn delete_saga(&mut self, saga: &'static str, id: &str) -> Result<(), Error> {
+        self.states.remove(&(saga, id.to_string()));
+        Ok(())
+    }
+}
+
+/// The [EventHandler] running a [Saga].
+pub(crate) struct SagaHandler<S>(PhantomData<fn() -> S>);
+
+impl<S> SagaHandler<S> {
+    pub(crate) const HANDLER: Self = Self(PhantomData);
+}
+
+#[async_trait]
+impl<S, C, E> EventHandler<C, E> for SagaHandler<S>
+where
+    S: Saga,
+    C: SagaStore + Send,
+    E: From<Error> + From<C::Error>,
+{
+    fn name(&self) -> &'static str {
+        S::NAME
+    }
+
+    fn event_names(&self) -> &[&'static str] {
+        S::EVENTS
+    }
+
+    async fn handle(&self, context: &mut C, event: &SerializedEvent) -> Result<Commands, E> {
+        let Some(id) = S::correlation_id(event)? else {
+            return Ok(Commands::new());
+        };
+        let mut saga = match context.load_saga(S::NAME, &id).await? {
+            Some(state) => serde_json::from_value::<S>(state)
+                .map_err(|error| Error::SagaSerializationError(S::NAME, error))?,
+            None if S::STARTING_EVENTS.contains(&event.name()) => S::default(),
+            None => return Ok(Commands::new()),
+        };
+        let commands = saga.handle(event.clone())?;
+        if saga.is_complete() {
+            context.delete_saga(S::NAME, &id).await?;
+        } else {
+            let state = serde_json::to_value(&saga)
+                .map_err(|error| Error::SagaSerializationError(S::NAME, error))?;
+            context.save_saga(S::NAME, &id, state).await?;
+        }
+        Ok(commands)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, commands, events, saga, Command, CommandBus, Configuration, Event,
+        EventWriter, Events,
+    };
+    use serde::Deserialize;
+
+    #[tokio::test]
+    async fn test_saga_lifecycle() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&place_order)
+                .command_handler(&reserve_stock)
+                .command_handler(&ship_order)
+                .saga::<OrderFulfillment>(),
+        );
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, PlaceOrder(1))
+            .await
+            .unwrap();
+        assert_eq!(context.sagas.count("order-fulfillment"), 1);
+        assert!(context.shipped.is_empty());
+
+        command_bus
+            .execute(&mut context, ReserveStock(1))
+            .await
+            .unwrap();
+        assert_eq!(context.sagas.count("order-fulfillment"), 0);
+        assert_eq!(context.shipped, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_events_without_saga_are_ignored() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&reserve_stock)
+                .command_handler(&ship_order)
+                .saga::<OrderFulfillment>(),
+        );
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, ReserveStock(2))
+            .await
+            .unwrap();
+
+        assert_eq!(context.sagas.count("order-fulfillment"), 0);
+        assert!(context.shipped.is_empty());
+    }
+
+    #[derive(Default, Serialize, Deserialize)]
+    struct OrderFulfillment {
+        placed: bool,
+        shipped: bool,
+    }
+
+    #[saga(correlation_id = order_id)]
+    impl OrderFulfillment {
+        #[saga(start)]
+        fn on_order_placed(&mut self, _: OrderPlaced) -> Commands {
+            self.placed = true;
+            commands!()
+        }
+
+        #[saga(handle)]
+        fn on_stock_reserved(&mut self, event: StockReserved) -> Commands {
+            self.shipped = true;
+            commands!(ShipOrder(event.order_id))
+        }
+
+        fn is_complete(&self) -> bool {
+            self.placed && self.shipped
+        }
+    }
+
+    #[derive(Default)]
+    struct TestContext {
+        sagas: InMemorySagaStore,
+        shipped: Vec<u32>,
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl SagaStore for TestContext {
+        type Error = Error;
+
+        async fn load_saga(
+            &mut self,
+            saga: &'static str,
+            id: &str,
+        ) -> Result<Option<Value>, Error> {
+            self.sagas.load_saga(saga, id).await
+        }
+
+        async fn save_saga(
+            &mut self,
+            saga: &'static str,
+            id: &str,
+            state: Value,
+        ) -> Result<(), Error> {
+            self.sagas.save_saga(saga, id, state).await
+        }
+
+        async fn delete_saga(&mut self, saga: &'static str, id: &str) -> Result<(), Error> {
+            self.sagas.delete_saga(saga, id).await
+        }
+    }
+
+    #[derive(Command)]
+    struct PlaceOrder(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct OrderPlaced {
+        order_id: u32,
+    }
+
+    #[command_handler]
+    async fn place_order(_: &mut TestContext, command: PlaceOrder) -> Result<Events, Error> {
+        Ok(events!(OrderPlaced {
+            order_id: command.0
+        }))
+    }
+
+    #[derive(Command)]
+    struct ReserveStock(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct StockReserved {
+        order_id: u32,
+    }
+
+    #[command_handler]
+    async fn reserve_stock(_: &mut TestContext, command: ReserveStock) -> Result<Events, Error> {
+        Ok(events!(StockReserved {
+            order_id: command.0
+        }))
+    }
+
+    #[derive(Command)]
+    struct ShipOrder(u32);
+
+    #[command_handler]
+    async fn ship_order(context: &mut TestContext, command: ShipOrder) -> Result<Events, Error> {
+        context.shipped.push(command.0);
+        Ok(Events::new())
+    }
+}
@@ -0,0 +1,567 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::any::type_name;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    BoxedCommand, Clock, Command, CommandHandler, Error, Events, SerializableCommand,
+    SerializedCommand, Validate, ValidationError,
+};
+
+/// A [command](Command) that schedules another command, to be executed later by
+/// [CommandBus::run_due](crate::CommandBus::run_due).
+///
+/// It is usually returned by an [event handler](crate::EventHandler). The scheduled command can be
+/// given a key, which allows to cancel it with [CancelSchedule]. Scheduling a command with the key
+/// of a pending command replaces it.
+///
+/// The scheduled command is validated when it is scheduled, and again when it is executed. It is
+/// kept in its [serialized form](SerializedCommand), so that the [ScheduleStore] can persist it: it
+/// must be a [SerializableCommand], registered with
+/// [serializable_command()](crate::Configuration::serializable_command).
+///
+/// # Example
+///
+/// ```
+/// # use presage::{commands, event_handler, Command, Commands, Event, Schedule, SerializableCommand};
+/// # use std::time::Duration;
+/// # #[derive(Event, serde::Serialize, serde::Deserialize)]
+/// # struct TodoChecked { id: u32 }
+/// # #[derive(Command, SerializableCommand, serde::Serialize, serde::Deserialize)]
+/// # struct ArchiveTodo { id: u32 }
+/// #[event_handler]
+/// async fn archive_checked_todo(_: &mut (), event: TodoChecked) -> Result<Commands, presage::Error> {
+///     let archive = ArchiveTodo { id: event.id };
+///     Ok(commands!(
+///         Schedule::after(Duration::from_secs(7 * 24 * 3600), archive).with_key(event.id.to_string())
+///     ))
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Schedule {
+    key: Option<String>,
+    due: Due,
+    command: BoxedCommand,
+    serialize: fn(&BoxedCommand) -> Result<SerializedCommand, Error>,
+}
+
+#[derive(Debug)]
+enum Due {
+    At(SystemTime),
+    After(Duration),
+}
+
+impl Schedule {
+    /// Schedules a command at the given time.
+    pub fn at(time: SystemTime, command: impl SerializableCommand) -> Self {
+        Self::new(Due::At(time), command)
+    }
+
+    /// Schedules a command after the given delay. The delay starts when the schedule is handled,
+    /// according to the [Clock] of the context.
+    pub fn after(delay: Duration, command: impl SerializableCommand) -> Self {
+        Self::new(Due::After(delay), command)
+    }
+
+    fn new<T: SerializableCommand>(due: Due, command: T) -> Self {
+        Self {
+            key: None,
+            due,
+            command: command.into(),
+            serialize: |command| {
+                command
+                    .downcast_ref::<T>()
+                    .ok_or(Error::CommandDowncastError(type_name::<T>()))
+                    .and_then(T::to_serialized)
+            },
+        }
+    }
+
+    /// Sets the key of the scheduled command. Takes ownership and returns the schedule to allow
+    /// chaining.
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+}
+
+impl Command for Schedule {
+    const NAME: &'static str = "presage-schedule";
+}
+
+impl Validate for Schedule {
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.command.validate()
+    }
+}
+
+/// A [command](Command) that cancels the pending scheduled command with the given key, if any.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CancelSchedule {
+    key: String,
+}
+
+impl CancelSchedule {
+    /// Creates a new cancellation for the given key.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+impl Command for CancelSchedule {
+    const NAME: &'static str = "presage-cancel-schedule";
+}
+
+impl Validate for CancelSchedule {}
+
+/// A command waiting in a [ScheduleStore] to be executed.
+///
+/// The command is kept in its [serialized form](SerializedCommand), and the scheduled command can
+/// itself be serialized, so that the store can persist it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    id: u64,
+    key: Option<String>,
+    due: SystemTime,
+    command: SerializedCommand,
+}
+
+impl ScheduledCommand {
+    /// Creates a scheduled command, with an optional key, due at the given time.
+    pub fn new(key: Option<String>, due: SystemTime, command: SerializedCommand) -> Self {
+        Self {
+            id: 0,
+            key,
+            due,
+            command,
+        }
+    }
+
+    /// Sets the id of the scheduled command. Used by [stores](ScheduleStore) when adding a
+    /// scheduled command. Takes ownership and returns the scheduled command to allow chaining.
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// The id of the scheduled command, unique within its store.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The key of the scheduled command, if any.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// The time at which the command is due.
+    pub fn due(&self) -> SystemTime {
+        self.due
+    }
+
+    /// The scheduled command.
+    pub fn command(&self) -> &SerializedCommand {
+        &self.command
+    }
+
+    /// Consumes the [ScheduledCommand] and returns the scheduled command.
+    pub fn into_command(self) -> SerializedCommand {
+        self.command
+    }
+}
+
+/// Persists the [scheduled commands](ScheduledCommand) until they are due.
+///
+/// It is implemented by the context of the [CommandBus](crate::CommandBus), which must also
+/// implement [Clock].
+///
+/// # Associated type
+///
+/// * [`Error`](Self::Error) - the type of errors returned if the store fails
+#[async_trait]
+pub trait ScheduleStore: Send {
+    /// Error returned when the store fails
+    type Error;
+
+    /// Adds a scheduled command, and gives it an [id](ScheduledCommand::id) that is unique within
+    /// the store. If the command has a key, it replaces the pending command with the same key, if
+    /// any.
+    async fn schedule(&mut self, command: ScheduledCommand) -> Result<(), Self::Error>;
+
+    /// Removes the pending command with the given key, if any.
+    async fn cancel(&mut self, key: &str) -> Result<(), Self::Error>;
+
+    /// Returns the pending commands that are due at the given time, the earliest first. The
+    /// commands stay in the store until they are [completed](Self::complete).
+    async fn due(&mut self, now: SystemTime) -> Result<Vec<ScheduledCommand>, Self::Error>;
+
+    /// Removes the pending command with the given id, once it has been successfully executed. The
+    /// command may have been cancelled, or replaced by another one with the same key, in the
+    /// meantime: its replacement must not be removed.
+    async fn complete(&mut self, id: u64) -> Result<(), Self::Error>;
+}
+
+/// A [ScheduleStore] that keeps the scheduled commands in memory.
+///
+/// It is meant to be embedded in a context that delegates its implementation of [ScheduleStore] to
+/// it.
+#[derive(Debug, Default)]
+pub struct InMemoryScheduleStore {
+    last_id: u64,
+    pending: Vec<ScheduledCommand>,
+}
+
+impl InMemoryScheduleStore {
+    /// Creates a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pending scheduled commands.
+    pub fn pending(&self) -> &[ScheduledCommand] {
+        &self.pending
+    }
+}
+
+#[async_trait]
+impl ScheduleStore for InMemoryScheduleStore {
+    type Error = Error;
+
+    async fn schedule(&mut self, command: ScheduledCommand) -> Result<(), Error> {
+        if let Some(key) = command.key() {
+            self.cancel(key).await?;
+        }
+        self.last_id += 1;
+        self.pending.push(command.with_id(self.last_id));
+        Ok(())
+    }
+
+    async fn cancel(&mut self, key: &str) -> Result<(), Error> {
+        self.pending.retain(|command| command.key() != Some(key));
+        Ok(())
+    }
+
+    async fn due(&mut self, now: SystemTime) -> Result<Vec<ScheduledCommand>, Error> {
+        let mut due: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|command| command.due <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|command| command.due);
+        Ok(due)
+    }
+
+    async fn complete(&mut self, id: u64) -> Result<(), Error> {
+        self.pending.retain(|command| command.id != id);
+        Ok(())
+    }
+}
+
+/// The [CommandHandler] of [Schedule].
+pub(crate) struct ScheduleHandler;
+
+#[async_trait]
+impl<C, E> CommandHandler<C, E> for ScheduleHandler
+where
+    C: ScheduleStore + Clock,
+    E: From<Error> + From<C::Error>,
+{
+    fn command_name(&self) -> &'static str {
+        Schedule::NAME
+    }
+
+    async fn handle(&self, context: &mut C, command: BoxedCommand) -> Result<Events, E> {
+        let schedule: Schedule = command.downcast()?;
+        let command = (schedule.serialize)(&schedule.command)?;
+        let due = match schedule.due {
+            Due::At(time) => time,
+            Due::After(delay) => context
+                .now()
+                .checked_add(delay)
+                .ok_or(Error::ScheduleOutOfRange(schedule.command.name()))?,
+        };
+        context
+            .schedule(ScheduledCommand::new(schedule.key, due, command))
+            .await?;
+        Ok(Events::new())
+    }
+}
+
+/// The [CommandHandler] of [CancelSchedule].
+pub(crate) struct CancelScheduleHandler;
+
+#[async_trait]
+impl<C, E> CommandHandler<C, E> for CancelScheduleHandler
+where
+    C: ScheduleStore + Clock,
+    E: From<Error> + From<C::Error>,
+{
+    fn command_name(&self) -> &'static str {
+        CancelSchedule::NAME
+    }
+
+    async fn handle(&self, context: &mut C, command: BoxedCommand) -> Result<Events, E> {
+        let CancelSchedule { key } = command.downcast()?;
+        context.cancel(&key).await?;
+        Ok(Events::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command_handler, commands, event_handler, events, CommandBus, Commands, Configuration,
+        Event, EventWriter, ManualClock, SerializableCommand, SerializedEvent,
+    };
+    use serde::{Deserialize, Serialize};
+
+    const WEEK: Duration = Duration::from_secs(7 * 24 * 3600);
+
+    #[tokio::test]
+    async fn test_scheduled_command_is_executed_when_due() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, CheckItem(1))
+            .await
+            .unwrap();
+        context.clock.advance(WEEK - Duration::from_secs(1));
+        let early = command_bus.run_due(&mut context).await.unwrap();
+        context.clock.advance(Duration::from_secs(1));
+        let due = command_bus.run_due(&mut context).await.unwrap();
+
+        assert_eq!((early, due), (0, 1));
+        assert_eq!(context.archived, vec![1]);
+        assert!(context.schedules.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_command_is_cancelled_by_key() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, CheckItem(1))
+            .await
+            .unwrap();
+        command_bus
+            .execute(&mut context, CheckItem(2))
+            .await
+            .unwrap();
+        command_bus
+            .execute(&mut context, CancelSchedule::new("archive-1"))
+            .await
+            .unwrap();
+        context.clock.advance(WEEK);
+        let executed = command_bus.run_due(&mut context).await.unwrap();
+
+        assert_eq!(executed, 1);
+        assert_eq!(context.archived, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_command_is_persisted_serialized() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, CheckItem(1))
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&context.schedules.pending()[0]).unwrap();
+        let scheduled: ScheduledCommand = serde_json::from_value(json).unwrap();
+
+        assert_eq!(scheduled.key(), Some("archive-1"));
+        assert_eq!(scheduled.due(), SystemTime::UNIX_EPOCH + WEEK);
+        assert_eq!(scheduled.command().name(), "archive-item");
+        assert_eq!(scheduled.command().payload(), &serde_json::json!(1));
+    }
+
+    #[tokio::test]
+    async fn test_delay_out_of_range_is_rejected() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        let error = command_bus
+            .execute(&mut context, Schedule::after(Duration::MAX, ArchiveItem(1)))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.into_inner(),
+            Error::ScheduleOutOfRange("archive-item")
+        ));
+        assert!(context.schedules.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_command_stays_pending() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(&mut context, CheckItem(1))
+            .await
+            .unwrap();
+        context.clock.advance(WEEK);
+        context.unavailable = true;
+        let error = command_bus.run_due(&mut context).await.unwrap_err();
+        let pending = context.schedules.pending().len();
+        context.unavailable = false;
+        let executed = command_bus.run_due(&mut context).await.unwrap();
+
+        assert_eq!(error.command(), Some("archive-item"));
+        assert_eq!(pending, 1);
+        assert_eq!(executed, 1);
+        assert_eq!(context.archived, vec![1]);
+        assert!(context.schedules.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_commands_due_at_the_start_are_executed() {
+        let command_bus = command_bus();
+        let mut context = TestContext::default();
+
+        command_bus
+            .execute(
+                &mut context,
+                Schedule::after(Duration::ZERO, Ping).with_key("ping"),
+            )
+            .await
+            .unwrap();
+        let executed = command_bus.run_due(&mut context).await.unwrap();
+
+        assert_eq!(executed, 1);
+        assert_eq!(context.pings, 1);
+        assert_eq!(context.schedules.pending().len(), 1);
+        assert_eq!(context.schedules.pending()[0].key(), Some("ping"));
+    }
+
+    fn command_bus() -> CommandBus<TestContext, Error> {
+        CommandBus::new().configure(
+            Configuration::new()
+                .scheduler()
+                .serializable_command::<ArchiveItem>()
+                .serializable_command::<Ping>()
+                .command_handler(&check_item)
+                .command_handler(&archive_item)
+                .command_handler(&ping)
+                .event_handler(&archive_checked_item)
+                .event_handler(&ping_again),
+        )
+    }
+
+    struct TestContext {
+        clock: ManualClock,
+        schedules: InMemoryScheduleStore,
+        archived: Vec<u32>,
+        unavailable: bool,
+        pings: u32,
+    }
+
+    impl Default for TestContext {
+        fn default() -> Self {
+            Self {
+                clock: ManualClock::new(SystemTime::UNIX_EPOCH),
+                schedules: InMemoryScheduleStore::new(),
+                archived: Vec::new(),
+                unavailable: false,
+                pings: 0,
+            }
+        }
+    }
+
+    impl Clock for TestContext {
+        fn now(&self) -> SystemTime {
+            self.clock.now()
+        }
+    }
+
+    #[async_trait]
+    impl ScheduleStore for TestContext {
+        type Error = Error;
+
+        async fn schedule(&mut self, command: ScheduledCommand) -> Result<(), Error> {
+            self.schedules.schedule(command).await
+        }
+
+        async fn cancel(&mut self, key: &str) -> Result<(), Error> {
+            self.schedules.cancel(key).await
+        }
+
+        async fn due(&mut self, now: SystemTime) -> Result<Vec<ScheduledCommand>, Error> {
+            self.schedules.due(now).await
+        }
+
+        async fn complete(&mut self, id: u64) -> Result<(), Error> {
+            self.schedules.complete(id).await
+        }
+    }
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    struct CheckItem(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct ItemChecked(u32);
+
+    #[command_handler]
+    async fn check_item(_: &mut TestContext, command: CheckItem) -> Result<Events, Error> {
+        Ok(events!(ItemChecked(command.0)))
+    }
+
+    #[event_handler]
+    async fn archive_checked_item(
+        _: &mut TestContext,
+        event: ItemChecked,
+    ) -> Result<Commands, Error> {
+        Ok(commands!(
+            Schedule::after(WEEK, ArchiveItem(event.0)).with_key(format!("archive-{}", event.0))
+        ))
+    }
+
+    #[derive(Command, SerializableCommand, Serialize, Deserialize)]
+    struct ArchiveItem(u32);
+
+    #[command_handler]
+    async fn archive_item(
+        context: &mut TestContext,
+        command: ArchiveItem,
+    ) -> Result<Events, Error> {
+        if context.unavailable {
+            return Err(Error::MissingCommandHandler("unavailable"));
+        }
+        context.archived.push(command.0);
+        Ok(Events::new())
+    }
+
+    #[derive(Command, SerializableCommand, Serialize, Deserialize)]
+    struct Ping;
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct Pinged;
+
+    #[command_handler]
+    async fn ping(context: &mut TestContext, _: Ping) -> Result<Events, Error> {
+        context.pings += 1;
+        Ok(events!(Pinged))
+    }
+
+    #[event_handler]
+    async fn ping_again(_: &mut TestContext, _: Pinged) -> Result<Commands, Error> {
+        Ok(commands!(
+            Schedule::after(Duration::ZERO, Ping).with_key("ping")
+        ))
+    }
+}
@@ -0,0 +1,51 @@
+//! Hidden macros used by the `command_handler` and `event_handler` macros to expose the schemas of
+//! the handled commands, and of the handled or emitted events, only when the `schema` feature is
+//! enabled.
+
+#[cfg(feature = "schema")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __command_schema {
+    ($command: ty) => {
+        fn command_schema(
+            &self,
+            generator: &mut $crate::schemars::SchemaGenerator,
+        ) -> Option<$crate::schemars::Schema> {
+            <$command as $crate::Command>::schema(generator)
+        }
+    };
+}
+
+#[cfg(not(feature = "schema"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __command_schema {
+    ($command: ty) => {};
+}
+
+#[cfg(feature = "schema")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_schemas {
+    ($($event: ty),* $(,)?) => {
+        fn event_schemas(
+            &self,
+            generator: &mut $crate::schemars::SchemaGenerator,
+        ) -> Vec<(&'static str, $crate::schemars::Schema)> {
+            let mut schemas = Vec::new();
+            $(
+                if let Some(schema) = <$event as $crate::Event>::schema(generator) {
+                    schemas.push((<$event as $crate::Event>::NAME, schema));
+                }
+            )*
+            schemas
+        }
+    };
+}
+
+#[cfg(not(feature = "schema"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_schemas {
+    ($($event: ty),* $(,)?) => {};
+}
@@ -0,0 +1,202 @@
+use futures_core::Stream;
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::sync::broadcast::{self, Sender};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::{Aggregate, Event, SerializedEvent};
+
+/// The default capacity of the channel of the subscriptions of a
+/// [CommandBus](crate::CommandBus).
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+/// The sending side of the subscriptions of a [CommandBus](crate::CommandBus).
+#[derive(Clone)]
+pub(crate) struct Subscriptions {
+    sender: Sender<SerializedEvent>,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            sender: broadcast::channel(capacity).0,
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> Subscription {
+        Subscription {
+            receiver: BroadcastStream::new(self.sender.subscribe()),
+            events: HashSet::new(),
+            missed: 0,
+        }
+    }
+
+    pub(crate) fn publish(&self, event: &SerializedEvent) {
+        // Without any subscriber, the event is simply dropped
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+/// An async [Stream] of the events written by a [CommandBus](crate::CommandBus).
+///
+/// Created with [CommandBus::subscribe()](crate::CommandBus::subscribe). The subscription receives
+/// every event written after its creation, unless it is filtered by [event](Self::event) or by
+/// [aggregate](Self::aggregate). The stream ends when every clone of the command bus is dropped.
+///
+/// The events are kept in a channel of limited capacity (see
+/// [Configuration::subscription_capacity()](crate::Configuration::subscription_capacity)). When a
+/// subscription is too slow to consume them, the oldest events are skipped, and counted by
+/// [missed()](Self::missed).
+///
+/// # Example
+///
+/// ```
+/// # use presage::{command_handler, events, Command, CommandBus, Configuration, Error, Event, Events};
+/// use tokio_stream::StreamExt;
+///
+/// # struct Context;
+/// # #[presage::async_trait]
+/// # impl presage::EventWriter for Context {
+/// #     type Error = Error;
+/// #     async fn write(&mut self, _: &presage::SerializedEvent) -> Result<(), Error> { Ok(()) }
+/// # }
+/// # #[derive(Command)]
+/// # struct CreateTodo;
+/// # #[derive(Event, serde::Serialize, serde::Deserialize)]
+/// # struct TodoCreated;
+/// # #[command_handler]
+/// # async fn create_todo(_: &mut Context, _: CreateTodo) -> Result<Events, Error> {
+/// #     Ok(events!(TodoCreated))
+/// # }
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let command_bus = CommandBus::new().configure(Configuration::new().command_handler(&create_todo));
+/// let mut subscription = command_bus.subscribe().event::<TodoCreated>();
+///
+/// command_bus.execute(&mut Context, CreateTodo).await.unwrap();
+///
+/// let event = subscription.next().await.unwrap();
+/// assert_eq!(event.name(), "todo-created");
+/// # }
+/// ```
+pub struct Subscription {
+    receiver: BroadcastStream<SerializedEvent>,
+    events: HashSet<&'static str>,
+    missed: u64,
+}
+
+impl Subscription {
+    /// Restricts the subscription to events of type `T`. Can be called several times to subscribe
+    /// to several types of events. Takes ownership and returns the subscription to allow chaining.
+    pub fn event<T: Event>(self) -> Self {
+        self.event_name(T::NAME)
+    }
+
+    /// Restricts the subscription to events with the given name. Can be called several times to
+    /// subscribe to several events. Takes ownership and returns the subscription to allow chaining.
+    pub fn event_name(mut self, name: &'static str) -> Self {
+        self.events.insert(name);
+        self
+    }
+
+    /// Restricts the subscription to the creation, update and deletion events of the aggregate `A`.
+    /// Takes ownership and returns the subscription to allow chaining.
+    pub fn aggregate<A: Aggregate>(self) -> Self {
+        self.event::<A::CreationEvent>()
+            .event::<A::UpdateEvent>()
+            .event::<A::DeletionEvent>()
+    }
+
+    /// The number of events that were skipped because the subscription lagged behind the command
+    /// bus.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    fn accepts(&self, event: &SerializedEvent) -> bool {
+        self.events.is_empty() || self.events.contains(event.name())
+    }
+}
+
+impl Stream for Subscription {
+    type Item = SerializedEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match ready!(Pin::new(&mut self.receiver).poll_next(cx)) {
+                Some(Ok(event)) if self.accepts(&event) => return Poll::Ready(Some(event)),
+                Some(Ok(_)) => {}
+                Some(Err(BroadcastStreamRecvError::Lagged(missed))) => self.missed += missed,
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{command_handler, events, CommandBus, Configuration, Error, EventWriter, Events};
+    use async_trait::async_trait;
+    use serde::{Deserialize, Serialize};
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_subscription_is_filtered_by_event() {
+        let command_bus = CommandBus::new().configure(configuration());
+        let mut subscription = command_bus.subscribe().event::<ItemRemoved>();
+
+        command_bus.execute(&mut Warehouse, MoveItem).await.unwrap();
+        drop(command_bus);
+
+        let events: Vec<_> = (&mut subscription).collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name(), ItemRemoved::NAME);
+        assert_eq!(subscription.missed(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscription_misses_oldest_events() {
+        let command_bus = CommandBus::new().configure(configuration().subscription_capacity(1));
+        let mut subscription = command_bus.subscribe();
+
+        command_bus.execute(&mut Warehouse, MoveItem).await.unwrap();
+        drop(command_bus);
+
+        let events: Vec<_> = (&mut subscription).collect().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name(), ItemAdded::NAME);
+        assert_eq!(subscription.missed(), 1);
+    }
+
+    fn configuration() -> Configuration<Warehouse, Error> {
+        Configuration::new().command_handler(&move_item)
+    }
+
+    struct Warehouse;
+
+    #[async_trait]
+    impl EventWriter for Warehouse {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(crate::Command)]
+    struct MoveItem;
+
+    #[derive(crate::Event, Serialize, Deserialize)]
+    struct ItemRemoved;
+
+    #[derive(crate::Event, Serialize, Deserialize)]
+    struct ItemAdded;
+
+    #[command_handler]
+    async fn move_item(_: &mut Warehouse, _: MoveItem) -> Result<Events, Error> {
+        Ok(events!(ItemRemoved, ItemAdded))
+    }
+}
@@ -0,0 +1,701 @@
+//! Utilities to test handlers.
+//!
+//! A [Scenario] tests a [command handler](crate::CommandHandler) in the given/when/then style:
+//! given the events that already happened, when a command is handled, then some events are
+//! expected, or an error.
+//!
+//! An [AggregateFixture] tests the lifecycle of an [Aggregate], by applying events to it.
+//!
+//! A [RecordingEventWriter] and a [FaultyEventWriter] can be used as contexts, or embedded in
+//! contexts, to check the events written by a [CommandBus](crate::CommandBus) and its behavior when
+//! writing fails.
+//!
+//! With the `proptest` feature, a [PropertyTest] executes sequences of commands generated by
+//! [proptest](::proptest) strategies, and checks invariants after each command.
+//!
+//! # Example
+//!
+//! ```
+//! use presage::testing::Scenario;
+//! use presage::{
+//!     async_trait, command_handler, events, Command, Configuration, Error, Event, EventWriter,
+//!     Events, SerializedEvent,
+//! };
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Default)]
+//! struct Context {
+//!     names: Vec<String>,
+//! }
+//!
+//! #[async_trait]
+//! impl EventWriter for Context {
+//!     type Error = Error;
+//!
+//!     async fn write(&mut self, event: &SerializedEvent) -> Result<(), Error> {
+//!         let event: TodoCreated = event.clone().deserialize()?;
+//!         self.names.push(event.name);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[derive(Command)]
+//! #[presage(validate)]
+//! struct CreateTodo {
+//!     #[validate(not_blank)]
+//!     name: String,
+//! }
+//!
+//! #[derive(Event, Serialize, Deserialize)]
+//! struct TodoCreated {
+//!     name: String,
+//! }
+//!
+//! #[command_handler]
+//! async fn create_todo(context: &mut Context, CreateTodo { name }: CreateTodo) -> Result<Events, Error> {
+//!     if context.names.contains(&name) {
+//!         Ok(Events::new())
+//!     } else {
+//!         Ok(events!(TodoCreated { name }))
+//!     }
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), Error> {
+//! let configuration = || Configuration::new().command_handler(&create_todo);
+//! let name = || "Write tests".to_string();
+//!
+//! Scenario::new(configuration(), Context::default())
+//!     .when(CreateTodo { name: name() })
+//!     .await
+//!     .then_expect(events!(TodoCreated { name: name() }));
+//!
+//! Scenario::new(configuration(), Context::default())
+//!     .given(events!(TodoCreated { name: name() }))
+//!     .when(CreateTodo { name: name() })
+//!     .await
+//!     .then_expect(Events::new());
+//!
+//! Scenario::new(configuration(), Context::default())
+//!     .when(CreateTodo { name: " ".into() })
+//!     .await
+//!     .then_error(|error| matches!(error, Error::InvalidCommand(..)));
+//! # Ok(())
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use std::fmt::{Debug, Write};
+
+#[cfg(feature = "proptest")]
+mod property;
+
+#[cfg(feature = "proptest")]
+pub use property::{arbitrary_command, ArbitraryCommand, PropertyTest};
+
+use crate::{
+    Aggregate, AggregateEvent, BoxedCommand, Command, Configuration, Error, Event, EventWriter,
+    Events, Id, SerializedEvent,
+};
+
+/// A given/when/then test of a [command handler](crate::CommandHandler).
+///
+/// The given events are written to the context with its [EventWriter], without being handled, so
+/// that the context is in the state expected by the tested command handler. The command is then
+/// validated and handled by the matching command handler of the [Configuration]. The events it
+/// returns are not written nor handled.
+///
+/// # Type arguments
+///
+/// * `C` - the context of the command handlers
+/// * `E` - the type of errors returned by the command handlers
+pub struct Scenario<C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    configuration: Configuration<C, E>,
+    context: C,
+    given: Events,
+}
+
+impl<C, E> Scenario<C, E>
+where
+    C: EventWriter<Error = E>,
+    E: From<Error> + Debug,
+{
+    /// Creates a new scenario for the command handlers of the configuration, with the given
+    /// context.
+    pub fn new(configuration: Configuration<C, E>, context: C) -> Self {
+        Self {
+            configuration,
+            context,
+            given: Events::new(),
+        }
+    }
+
+    /// Adds events that happened before the command. Takes ownership and returns the scenario to
+    /// allow chaining.
+    pub fn given(mut self, events: Events) -> Self {
+        self.given.0.extend(events);
+        self
+    }
+
+    /// Writes the given events, then validates and handles the command.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the given events cannot be written.
+    pub async fn when<T>(mut self, command: T) -> Outcome<C, E>
+    where
+        T: Command,
+    {
+        for event in &self.given.0 {
+            if let Err(error) = self.context.write(event).await {
+                panic!("Could not write given event {}: {error:?}", event.name());
+            }
+        }
+        let command: BoxedCommand = command.into();
+        let result = match self.configuration.command_handlers.get(T::NAME) {
+            None => Err(Error::MissingCommandHandler(T::NAME).into()),
+            Some(handler) => match command.validate() {
+                Err(error) => Err(Error::InvalidCommand(T::NAME, error).into()),
+                Ok(()) => handler.handle(&mut self.context, command).await,
+            },
+        };
+        Outcome {
+            context: self.context,
+            result,
+        }
+    }
+}
+
+/// The outcome of a [Scenario], on which expectations are checked.
+#[derive(Debug)]
+pub struct Outcome<C, E> {
+    context: C,
+    result: Result<Events, E>,
+}
+
+impl<C, E> Outcome<C, E>
+where
+    E: Debug,
+{
+    /// The result of the command handler.
+    pub fn result(&self) -> &Result<Events, E> {
+        &self.result
+    }
+
+    /// Checks that the command handler returned the expected events, in the same order, and returns
+    /// the context.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a diff of the expected and returned events if they do not match, or if the
+    /// command handler failed.
+    pub fn then_expect(self, expected: Events) -> C {
+        match self.result {
+            Ok(events) if events == expected => self.context,
+            Ok(events) => panic!(
+                "The returned events do not match the expected events:\n{}",
+                diff(&expected, &events)
+            ),
+            Err(error) => panic!("Expected events, but the command failed: {error:?}"),
+        }
+    }
+
+    /// Checks that the command handler failed with an error matching the predicate, and returns the
+    /// context.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the command handler succeeded, or if the error does not match the predicate.
+    pub fn then_error(self, predicate: impl FnOnce(&E) -> bool) -> C {
+        match self.result {
+            Err(error) if predicate(&error) => self.context,
+            Err(error) => panic!("The error does not match the expectation: {error:?}"),
+            Ok(events) => panic!(
+                "Expected an error, but the command succeeded with events:\n{}",
+                diff(&Events::new(), &events)
+            ),
+        }
+    }
+}
+
+/// A fixture that builds an [Aggregate] from its events, and checks its state.
+///
+/// The fixture checks that every event applied to the aggregate has the same
+/// [id](AggregateEvent::id) as the aggregate, and that no event is applied once the aggregate has
+/// been deleted.
+///
+/// # Example
+///
+/// ```
+/// use presage::testing::AggregateFixture;
+/// # use presage::{Aggregate, AggregateEvent, Id};
+/// # #[derive(serde::Serialize, serde::Deserialize, AggregateEvent)]
+/// # #[presage(Todo)]
+/// # pub struct TodoCreated { #[id] id: Id<Todo>, name: String }
+/// # #[derive(serde::Serialize, serde::Deserialize, AggregateEvent)]
+/// # #[presage(aggregate = Todo, id = id)]
+/// # pub enum TodoUpdated { Renamed { #[id] id: Id<Todo>, new_name: String } }
+/// # #[derive(serde::Serialize, serde::Deserialize, AggregateEvent)]
+/// # #[presage(Todo)]
+/// # pub struct TodoDeleted(#[id] Id<Todo>);
+/// # pub struct Todo { id: Id<Todo>, name: String }
+/// # impl Aggregate for Todo {
+/// #     type Id = u64;
+/// #     type CreationEvent = TodoCreated;
+/// #     type UpdateEvent = TodoUpdated;
+/// #     type DeletionEvent = TodoDeleted;
+/// #     fn id(&self) -> Id<Self> { self.id }
+/// #     fn new(event: TodoCreated) -> Self { Self { id: event.id, name: event.name } }
+/// #     fn apply(&mut self, event: TodoUpdated) {
+/// #         match event { TodoUpdated::Renamed { new_name, .. } => self.name = new_name }
+/// #     }
+/// # }
+///
+/// AggregateFixture::new(TodoCreated { id: Id(1), name: "Write tests".into() })
+///     .apply(TodoUpdated::Renamed { id: Id(1), new_name: "Write more tests".into() })
+///     .then(|todo| assert_eq!(todo.name, "Write more tests"))
+///     .delete(TodoDeleted(Id(1)))
+///     .then_deleted();
+/// ```
+pub struct AggregateFixture<A>
+where
+    A: Aggregate,
+{
+    aggregate: A,
+    deleted: bool,
+}
+
+impl<A> AggregateFixture<A>
+where
+    A: Aggregate,
+    A::Id: PartialEq + Debug,
+{
+    /// Creates the aggregate from its creation event.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the created aggregate does not have the id of the event.
+    #[track_caller]
+    pub fn new<T>(event: T) -> Self
+    where
+        T: AggregateEvent<Aggregate = A>,
+        A: Aggregate<CreationEvent = T>,
+    {
+        let id = event.id();
+        let aggregate = A::new(event);
+        check_id::<T>(&aggregate, id);
+        Self {
+            aggregate,
+            deleted: false,
+        }
+    }
+
+    /// Applies an update event to the aggregate. Takes ownership and returns the fixture to allow
+    /// chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the aggregate has been deleted, or if the event has a different id.
+    #[track_caller]
+    pub fn apply(mut self, event: A::UpdateEvent) -> Self {
+        self.check_not_deleted::<A::UpdateEvent>();
+        check_id::<A::UpdateEvent>(&self.aggregate, event.id());
+        self.aggregate.apply(event);
+        self
+    }
+
+    /// Applies update events to the aggregate, in order. Takes ownership and returns the fixture to
+    /// allow chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the aggregate has been deleted, or if one of the events has a different id.
+    #[track_caller]
+    pub fn apply_all(self, events: impl IntoIterator<Item = A::UpdateEvent>) -> Self {
+        events.into_iter().fold(self, Self::apply)
+    }
+
+    /// Deletes the aggregate, which ends its lifecycle. Takes ownership and returns the fixture to
+    /// allow chaining.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the aggregate has already been deleted, or if the event has a different id.
+    #[track_caller]
+    pub fn delete(mut self, event: A::DeletionEvent) -> Self {
+        self.check_not_deleted::<A::DeletionEvent>();
+        check_id::<A::DeletionEvent>(&self.aggregate, event.id());
+        self.deleted = true;
+        self
+    }
+
+    /// Checks the state of the aggregate with the given assertions. Takes ownership and returns the
+    /// fixture to allow chaining.
+    pub fn then(self, assertions: impl FnOnce(&A)) -> Self {
+        assertions(&self.aggregate);
+        self
+    }
+
+    /// Checks that the aggregate has been deleted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the aggregate has not been deleted.
+    #[track_caller]
+    pub fn then_deleted(self) {
+        assert!(
+            self.deleted,
+            "Aggregate {} has not been deleted",
+            self.aggregate.id()
+        );
+    }
+
+    /// The current state of the aggregate.
+    pub fn aggregate(&self) -> &A {
+        &self.aggregate
+    }
+
+    /// Whether the aggregate has been deleted.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Consumes the fixture and returns the aggregate.
+    pub fn into_aggregate(self) -> A {
+        self.aggregate
+    }
+
+    #[track_caller]
+    fn check_not_deleted<T: AggregateEvent>(&self) {
+        assert!(
+            !self.deleted,
+            "Cannot apply {} to aggregate {}, which has been deleted",
+            T::NAME,
+            self.aggregate.id()
+        );
+    }
+}
+
+#[track_caller]
+fn check_id<T>(aggregate: &T::Aggregate, id: Id<T::Aggregate>)
+where
+    T: AggregateEvent,
+    <T::Aggregate as Aggregate>::Id: PartialEq + Debug,
+{
+    assert_eq!(
+        aggregate.id().0,
+        id.0,
+        "Event {} was routed to the wrong aggregate",
+        T::NAME
+    );
+}
+
+/// An [EventWriter] that records the written events.
+///
+/// # Example
+///
+/// ```
+/// use presage::testing::RecordingEventWriter;
+/// use presage::{command_handler, events, Command, CommandBus, Configuration, Error, Event, Events};
+///
+/// #[derive(Command)]
+/// struct CreateTodo(String);
+///
+/// #[derive(Debug, PartialEq, Event, serde::Serialize, serde::Deserialize)]
+/// struct TodoCreated(String);
+///
+/// #[command_handler]
+/// async fn create_todo<C: Send>(_: &mut C, CreateTodo(name): CreateTodo) -> Result<Events, Error> {
+///     Ok(events!(TodoCreated(name)))
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let command_bus = CommandBus::new().configure(Configuration::new().command_handler(&create_todo));
+/// let mut writer = RecordingEventWriter::new();
+///
+/// command_bus.execute(&mut writer, CreateTodo("Write tests".into())).await.unwrap();
+///
+/// assert_eq!(writer.events_of::<TodoCreated>(), vec![TodoCreated("Write tests".into())]);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordingEventWriter {
+    events: Vec<SerializedEvent>,
+}
+
+impl RecordingEventWriter {
+    /// Creates a new writer, without any recorded event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The recorded events, in the order in which they were written.
+    pub fn events(&self) -> &[SerializedEvent] {
+        &self.events
+    }
+
+    /// The recorded events of the given type, deserialized, in the order in which they were
+    /// written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the events cannot be deserialized.
+    #[track_caller]
+    pub fn events_of<T: Event>(&self) -> Vec<T> {
+        self.events
+            .iter()
+            .filter(|event| event.name() == T::NAME)
+            .map(|event| match event.clone().deserialize() {
+                Ok(event) => event,
+                Err(error) => panic!("{error}"),
+            })
+            .collect()
+    }
+
+    /// Forgets the recorded events.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+#[async_trait]
+impl EventWriter for RecordingEventWriter {
+    type Error = Error;
+
+    async fn write(&mut self, event: &SerializedEvent) -> Result<(), Error> {
+        self.events.push(event.clone());
+        Ok(())
+    }
+}
+
+/// An [EventWriter] that fails to write the Nth event, starting at 1. The other events are
+/// recorded, like with a [RecordingEventWriter].
+///
+/// The failure is returned as an [Error::EventWriterError].
+#[derive(Debug, Clone)]
+pub struct FaultyEventWriter {
+    recorder: RecordingEventWriter,
+    fail_on: usize,
+    writes: usize,
+}
+
+impl FaultyEventWriter {
+    /// Creates a new writer that fails to write the Nth event.
+    pub fn failing_on(write: usize) -> Self {
+        Self {
+            recorder: RecordingEventWriter::new(),
+            fail_on: write,
+            writes: 0,
+        }
+    }
+
+    /// The number of writes attempted so far, including the failed one.
+    pub fn writes(&self) -> usize {
+        self.writes
+    }
+
+    /// The recorder of the successfully written events.
+    pub fn recorder(&self) -> &RecordingEventWriter {
+        &self.recorder
+    }
+}
+
+#[async_trait]
+impl EventWriter for FaultyEventWriter {
+    type Error = Error;
+
+    async fn write(&mut self, event: &SerializedEvent) -> Result<(), Error> {
+        self.writes += 1;
+        if self.writes == self.fail_on {
+            Err(Error::EventWriterError(format!(
+                "injected failure on write {} ({})",
+                self.writes,
+                event.name()
+            )))
+        } else {
+            self.recorder.write(event).await
+        }
+    }
+}
+
+/// Formats a line-by-line diff of two lists of events. Expected events that are missing are
+/// prefixed with `-`, and unexpected events with `+`.
+fn diff(expected: &Events, actual: &Events) -> String {
+    let mut diff = String::new();
+    for index in 0..expected.0.len().max(actual.0.len()) {
+        match (expected.0.get(index), actual.0.get(index)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                let _ = writeln!(diff, "  {}", describe(expected));
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    let _ = writeln!(diff, "- {}", describe(expected));
+                }
+                if let Some(actual) = actual {
+                    let _ = writeln!(diff, "+ {}", describe(actual));
+                }
+            }
+        }
+    }
+    diff
+}
+
+fn describe(event: &SerializedEvent) -> String {
+    if event.metadata().is_empty() {
+        format!("{} {}", event.name(), event.value())
+    } else {
+        format!(
+            "{} {} {}",
+            event.name(),
+            event.value(),
+            serde_json::Value::Object(event.metadata().clone())
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{command_handler, events, AggregateEvent, CommandBus};
+    use serde::{Deserialize, Serialize};
+
+    #[tokio::test]
+    async fn test_faulty_writer_stops_the_execution() {
+        let command_bus =
+            CommandBus::new().configure(Configuration::new().command_handler(&create_items));
+        let mut writer = FaultyEventWriter::failing_on(2);
+
+        let error = command_bus
+            .execute(&mut writer, CreateItems)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.event(), Some("item-created"));
+        assert!(matches!(error.error(), Error::EventWriterError(_)));
+        assert_eq!(writer.writes(), 2);
+        assert_eq!(
+            writer.recorder().events_of::<ItemCreated>(),
+            vec![ItemCreated(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commands_without_written_events_are_not_compensated() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_items)
+                .command_handler(&delete_items)
+                .compensation(|_: &CreateItems| DeleteItems),
+        );
+        let mut writer = FaultyEventWriter::failing_on(1);
+
+        let error = command_bus
+            .execute(&mut writer, CreateItems)
+            .await
+            .unwrap_err();
+
+        assert!(error.compensations().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commands_with_written_events_are_compensated() {
+        let command_bus = CommandBus::new().configure(
+            Configuration::new()
+                .command_handler(&create_items)
+                .command_handler(&delete_items)
+                .compensation(|_: &CreateItems| DeleteItems),
+        );
+        let mut writer = FaultyEventWriter::failing_on(2);
+
+        let error = command_bus
+            .execute(&mut writer, CreateItems)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.compensations().len(), 1);
+        assert_eq!(error.compensations()[0].command(), "delete-items");
+    }
+
+    #[test]
+    #[should_panic(expected = "Event item-renamed was routed to the wrong aggregate")]
+    fn test_fixture_rejects_misrouted_events() {
+        AggregateFixture::new(ItemAdded(Id(1))).apply(ItemRenamed(Id(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot apply item-renamed to aggregate 1, which has been deleted")]
+    fn test_fixture_rejects_events_after_deletion() {
+        AggregateFixture::new(ItemAdded(Id(1)))
+            .delete(ItemRemoved(Id(1)))
+            .apply(ItemRenamed(Id(1)));
+    }
+
+    #[derive(Debug, PartialEq, Event, Serialize, Deserialize)]
+    struct ItemCreated(u32);
+
+    #[derive(Command)]
+    struct CreateItems;
+
+    #[command_handler]
+    async fn create_items<C: Send>(_: &mut C, _: CreateItems) -> Result<Events, Error> {
+        Ok(events!(ItemCreated(1), ItemCreated(2), ItemCreated(3)))
+    }
+
+    #[derive(Command)]
+    struct DeleteItems;
+
+    #[command_handler]
+    async fn delete_items<C: Send>(_: &mut C, _: DeleteItems) -> Result<Events, Error> {
+        Ok(Events::new())
+    }
+
+    #[test]
+    fn test_diff_marks_mismatching_events() -> Result<(), Error> {
+        let diff = diff(
+            &events!(ItemCreated(1), ItemCreated(2)),
+            &events!(ItemCreated(1), ItemCreated(3), ItemCreated(4)),
+        );
+
+        assert_eq!(
+            diff,
+            "  item-created 1\n- item-created 2\n+ item-created 3\n+ item-created 4\n"
+        );
+        Ok(())
+    }
+
+    struct Item(Id<Item>);
+
+    impl Aggregate for Item {
+        type Id = u32;
+        type CreationEvent = ItemAdded;
+        type UpdateEvent = ItemRenamed;
+        type DeletionEvent = ItemRemoved;
+
+        fn id(&self) -> Id<Self> {
+            self.0
+        }
+
+        fn new(event: ItemAdded) -> Self {
+            Self(event.0)
+        }
+
+        fn apply(&mut self, _: ItemRenamed) {}
+    }
+
+    #[derive(Serialize, Deserialize, AggregateEvent)]
+    #[presage(Item)]
+    struct ItemAdded(#[id] Id<Item>);
+
+    #[derive(Serialize, Deserialize, AggregateEvent)]
+    #[presage(Item)]
+    struct ItemRenamed(#[id] Id<Item>);
+
+    #[derive(Serialize, Deserialize, AggregateEvent)]
+    #[presage(Item)]
+    struct ItemRemoved(#[id] Id<Item>);
+}
@@ -0,0 +1,311 @@
+use proptest::strategy::{BoxedStrategy, Strategy, ValueTree};
+use proptest::test_runner::{Config, TestRunner};
+use std::fmt::{Debug, Formatter, Write};
+use std::sync::Arc;
+
+use crate::{BoxedCommand, Command, CommandBus, Configuration, Error, EventWriter};
+
+/// A command generated by a [proptest] strategy, to be executed by a [PropertyTest].
+///
+/// Created with [arbitrary_command].
+#[derive(Clone)]
+pub struct ArbitraryCommand {
+    description: String,
+    create: Arc<dyn Fn() -> BoxedCommand + Send + Sync>,
+}
+
+impl Debug for ArbitraryCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+/// Wraps a [proptest] strategy of commands into a strategy of [ArbitraryCommand], so that
+/// strategies of different command types can be combined, e.g., with `prop_oneof!`.
+pub fn arbitrary_command<T>(
+    strategy: impl Strategy<Value = T> + 'static,
+) -> BoxedStrategy<ArbitraryCommand>
+where
+    T: Command + Clone + Debug,
+{
+    strategy
+        .prop_map(|command| ArbitraryCommand {
+            description: format!("{command:?}"),
+            create: Arc::new(move || command.clone().into()),
+        })
+        .boxed()
+}
+
+type Invariant<C> = Box<dyn Fn(&C) -> bool>;
+
+/// A property-based test of a [Configuration].
+///
+/// Each test case executes a generated sequence of commands against a new context, and checks the
+/// invariants after each command. Commands that fail are not failures of the test: the invariants
+/// must hold whatever the outcome of the commands. When an invariant is violated, the sequence of
+/// commands is shrunk, and the test panics with the minimal failing sequence.
+///
+/// # Type arguments
+///
+/// * `C` - the context of the handlers
+/// * `E` - the type of errors returned by the handlers
+///
+/// # Example
+///
+/// ```
+/// use presage::testing::{arbitrary_command, PropertyTest, RecordingEventWriter};
+/// use presage::{command_handler, events, Command, Configuration, Error, Event, Events};
+/// use proptest::prelude::*;
+///
+/// #[derive(Debug, Clone, Command)]
+/// struct Deposit(u32);
+///
+/// #[derive(Debug, Clone, Command)]
+/// struct Withdraw(u32);
+///
+/// #[derive(Event, serde::Serialize, serde::Deserialize)]
+/// struct BalanceChanged(i64);
+///
+/// #[command_handler]
+/// async fn deposit<C: Send>(_: &mut C, Deposit(amount): Deposit) -> Result<Events, Error> {
+///     Ok(events!(BalanceChanged(amount as i64)))
+/// }
+///
+/// #[command_handler]
+/// async fn withdraw<C: Send>(_: &mut C, Withdraw(amount): Withdraw) -> Result<Events, Error> {
+///     Ok(events!(BalanceChanged(-(amount as i64))))
+/// }
+///
+/// fn balance(writer: &RecordingEventWriter) -> i64 {
+///     writer.events_of::<BalanceChanged>().iter().map(|change| change.0).sum()
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let property = PropertyTest::new(
+///     Configuration::new()
+///         .command_handler(&deposit)
+///         .command_handler(&withdraw),
+///     RecordingEventWriter::new,
+/// )
+/// .invariant("the balance is never negative", |writer| balance(writer) >= 0);
+///
+/// let commands = prop::collection::vec(
+///     prop_oneof![
+///         arbitrary_command((0..100u32).prop_map(Deposit)),
+///         arbitrary_command((0..100u32).prop_map(Withdraw)),
+///     ],
+///     0..10,
+/// );
+///
+/// // The withdrawals are not checked, so the property does not hold
+/// let result = property.run(commands).await;
+/// assert!(result.is_err());
+/// # }
+/// ```
+pub struct PropertyTest<C, E>
+where
+    C: 'static,
+    E: 'static,
+{
+    command_bus: CommandBus<C, E>,
+    context: Box<dyn Fn() -> C>,
+    invariants: Vec<(&'static str, Invariant<C>)>,
+    config: Config,
+}
+
+impl<C, E> PropertyTest<C, E>
+where
+    C: EventWriter<Error = E>,
+    E: From<Error> + Debug,
+{
+    /// Creates a new property-based test of the configuration. Each test case runs against a new
+    /// context, created with the given function.
+    pub fn new(configuration: Configuration<C, E>, context: impl Fn() -> C + 'static) -> Self {
+        Self {
+            command_bus: CommandBus::new().configure(configuration),
+            context: Box::new(context),
+            invariants: Vec::new(),
+            config: Config::default(),
+        }
+    }
+
+    /// Adds an invariant, which must hold after each command. Takes ownership and returns the test
+    /// to allow chaining.
+    pub fn invariant(
+        mut self,
+        description: &'static str,
+        invariant: impl Fn(&C) -> bool + 'static,
+    ) -> Self {
+        self.invariants.push((description, Box::new(invariant)));
+        self
+    }
+
+    /// Sets the [proptest] configuration, e.g., to change the number of test cases. Takes ownership
+    /// and returns the test to allow chaining.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Runs the test cases with sequences of commands generated by the strategy. If an invariant is
+    /// violated, returns the description of the minimal failing sequence.
+    pub async fn run(
+        &self,
+        strategy: impl Strategy<Value = Vec<ArbitraryCommand>>,
+    ) -> Result<(), String> {
+        let mut runner = TestRunner::new(self.config.clone());
+        for _ in 0..self.config.cases {
+            let mut tree = strategy
+                .new_tree(&mut runner)
+                .map_err(|reason| format!("Could not generate commands: {reason}"))?;
+            if let Err(failure) = self.run_case(tree.current()).await {
+                return Err(self.shrink(&mut tree, failure).await);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the test cases like [run()](Self::run).
+    ///
+    /// # Panics
+    ///
+    /// Panics with the minimal failing sequence if an invariant is violated.
+    pub async fn check(&self, strategy: impl Strategy<Value = Vec<ArbitraryCommand>>) {
+        if let Err(failure) = self.run(strategy).await {
+            panic!("{failure}");
+        }
+    }
+
+    async fn shrink(
+        &self,
+        tree: &mut impl ValueTree<Value = Vec<ArbitraryCommand>>,
+        mut failure: Failure,
+    ) -> String {
+        let mut iterations = 0;
+        let mut simplified = tree.simplify();
+        while simplified && iterations < self.config.max_shrink_iters {
+            iterations += 1;
+            simplified = match self.run_case(tree.current()).await {
+                Err(smaller) => {
+                    failure = smaller;
+                    tree.simplify()
+                }
+                Ok(()) => tree.complicate(),
+            };
+        }
+        failure.describe()
+    }
+
+    async fn run_case(&self, commands: Vec<ArbitraryCommand>) -> Result<(), Failure> {
+        let mut context = (self.context)();
+        for (step, command) in commands.iter().enumerate() {
+            let outcome = self
+                .command_bus
+                .execute_boxed(&mut context, (command.create)())
+                .await
+                .map_err(|error| format!("{error:?}"));
+            if let Some((invariant, _)) = self
+                .invariants
+                .iter()
+                .find(|(_, invariant)| !invariant(&context))
+            {
+                return Err(Failure {
+                    invariant,
+                    commands: commands[..=step].to_vec(),
+                    outcome,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Failure {
+    invariant: &'static str,
+    commands: Vec<ArbitraryCommand>,
+    outcome: Result<(), String>,
+}
+
+impl Failure {
+    fn describe(&self) -> String {
+        let mut description = format!(
+            "Invariant \"{}\" is violated after the following commands:\n",
+            self.invariant
+        );
+        for (step, command) in self.commands.iter().enumerate() {
+            let _ = writeln!(description, "{}. {command:?}", step + 1);
+        }
+        if let Err(error) = &self.outcome {
+            let _ = writeln!(description, "The last command failed: {error}");
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::RecordingEventWriter;
+    use crate::{command_handler, events, Event, Events};
+    use proptest::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[tokio::test]
+    async fn test_failing_sequence_is_shrunk() {
+        let property = PropertyTest::new(
+            Configuration::new()
+                .command_handler(&add_item)
+                .command_handler(&remove_item),
+            RecordingEventWriter::new,
+        )
+        .invariant("the stock is never negative", |writer| stock(writer) >= 0);
+
+        let failure = property
+            .run(prop::collection::vec(
+                prop_oneof![
+                    arbitrary_command((0..10u32).prop_map(AddItem)),
+                    arbitrary_command((0..10u32).prop_map(RemoveItem)),
+                ],
+                1..10,
+            ))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            failure,
+            "Invariant \"the stock is never negative\" is violated after the following commands:\n\
+             1. RemoveItem(1)\n"
+        );
+    }
+
+    fn stock(writer: &RecordingEventWriter) -> i64 {
+        writer
+            .events_of::<StockChanged>()
+            .iter()
+            .map(|change| change.0)
+            .sum()
+    }
+
+    #[derive(Debug, Clone, Command)]
+    struct AddItem(u32);
+
+    #[derive(Debug, Clone, Command)]
+    struct RemoveItem(u32);
+
+    #[derive(Event, Serialize, Deserialize)]
+    struct StockChanged(i64);
+
+    #[command_handler]
+    async fn add_item<C: Send>(_: &mut C, AddItem(count): AddItem) -> Result<Events, Error> {
+        Ok(events!(StockChanged(count.into())))
+    }
+
+    #[command_handler]
+    async fn remove_item<C: Send>(
+        _: &mut C,
+        RemoveItem(count): RemoveItem,
+    ) -> Result<Events, Error> {
+        Ok(events!(StockChanged(-i64::from(count))))
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt::{Display, Formatter};
+
+/// Validates a [command](crate::Command) before it is handled.
+///
+/// The [CommandBus](crate::CommandBus) validates every command before executing its handler. If
+/// the validation fails, the handler is not executed and an
+/// [InvalidCommand](crate::Error::InvalidCommand) error is returned.
+///
+/// The default implementation accepts any command. When deriving [Command](crate::Command), this
+/// trait is implemented automatically. Adding the `#[presage(validate)]` attribute on the type
+/// generates the validation from rules declared on the fields with the `#[validate]` attribute,
+/// while `#[presage(validate = manual)]` leaves the implementation to be written by hand, e.g., for
+/// rules involving several fields.
+///
+/// # Example
+///
+/// ```
+/// use presage::{Validate, ValidationError};
+///
+/// pub struct CreateTodo {
+///     pub name: String,
+/// }
+///
+/// impl Validate for CreateTodo {
+///     fn validate(&self) -> Result<(), ValidationError> {
+///         let mut error = ValidationError::new();
+///         if self.name.is_empty() {
+///             error.add("name", "must not be empty");
+///         }
+///         error.into_result()
+///     }
+/// }
+/// ```
+pub trait Validate {
+    /// Checks the validity of the command. Must return all the problems at once.
+    fn validate(&self) -> Result<(), ValidationError> {
+        Ok(())
+    }
+}
+
+/// The problems found when [validating](Validate) a command.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidationError {
+    fields: Vec<FieldError>,
+}
+
+impl ValidationError {
+    /// Creates a new [ValidationError], without any problem.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a problem for the given field.
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.fields.push(FieldError {
+            field,
+            message: message.into(),
+        })
+    }
+
+    /// The problems, by field.
+    pub fn fields(&self) -> &[FieldError] {
+        &self.fields
+    }
+
+    /// Returns `true` if no problem has been added.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns `Ok(())` if no problem has been added, otherwise returns the error.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, field) in self.fields.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{field}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A problem found on a single field of a command.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldError {
+    /// The name of the invalid field.
+    pub field: &'static str,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        async_trait, command_handler, Command, CommandBus, Configuration, Error, EventWriter,
+        Events, ExecutionError, SerializedEvent,
+    };
+
+    #[test]
+    fn test_derived_validation_reports_all_fields() {
+        let command = CreateItem {
+            name: " ".into(),
+            tags: vec![],
+            quantity: 0,
+        };
+
+        let error = command.validate().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "name must not be blank, tags must not be empty, quantity must be greater than or equal to 1"
+        );
+    }
+
+    #[test]
+    fn test_manual_validation() {
+        let command = MoveItem { from: 2, to: 2 };
+
+        let error = command.validate().unwrap_err();
+
+        assert_eq!(error.to_string(), "to must differ from from");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_command_is_not_handled() {
+        let command_bus =
+            CommandBus::new().configure(Configuration::new().command_handler(&create_item));
+        let command = CreateItem {
+            name: "item".into(),
+            tags: vec!["tag".into()],
+            quantity: 11,
+        };
+
+        let result = command_bus
+            .execute(&mut TestContext, command)
+            .await
+            .map_err(ExecutionError::into_inner);
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidCommand("create-item", _))
+        ));
+    }
+
+    struct TestContext;
+
+    #[async_trait]
+    impl EventWriter for TestContext {
+        type Error = Error;
+
+        async fn write(&mut self, _: &SerializedEvent) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[derive(Command)]
+    #[presage(validate)]
+    struct CreateItem {
+        #[validate(not_blank)]
+        name: String,
+        #[validate(non_empty)]
+        tags: Vec<String>,
+        #[validate(range(min = 1, max = 10))]
+        quantity: u32,
+    }
+
+    #[derive(Command)]
+    #[presage(validate = manual)]
+    struct MoveItem {
+        from: u32,
+        to: u32,
+    }
+
+    impl Validate for MoveItem {
+        fn validate(&self) -> Result<(), ValidationError> {
+            let mut error = ValidationError::new();
+            if self.from == self.to {
+                error.add("to", "must differ from from");
+            }
+            error.into_result()
+        }
+    }
+
+    #[command_handler]
+    async fn create_item(_: &mut TestContext, _: CreateItem) -> Result<Events, Error> {
+        panic!("an invalid command must not be handled")
+    }
+}